@@ -1,15 +1,21 @@
 //! mostly taken from [harryplusplus/kill_tree](https://github.com/harryplusplus/kill-tree/blob/main/crates/libs/kill_tree/)
 
-// TODO: linux/mac?
+// TODO: mac?
 
 #[cfg(windows)]
 mod windows;
 
+#[cfg(target_os = "linux")]
+mod linux;
+
 use std::collections::{HashMap, VecDeque};
 
 #[cfg(windows)]
 use windows as imp;
 
+#[cfg(target_os = "linux")]
+use linux as imp;
+
 #[derive(Debug, Clone)]
 pub struct ProcessInfo {
     pub name: String,
@@ -51,6 +57,42 @@ pub fn get_children(parent: u32) -> anyhow::Result<Vec<ProcessInfo>> {
     Ok(children)
 }
 
+/// spawns `sh -> (sh -> sleep), sleep` in its own process group, returning it once its three descendants are listed.
+///
+/// only for tests, here and in crates that enable the `test-tree` feature.
+///
+/// # Panics
+///
+/// Will panic if `sh` can't be spawned, or the processes can't be listed.
+#[cfg(all(target_os = "linux", any(test, feature = "test-tree")))]
+pub fn spawn_test_tree() -> (std::process::Child, Vec<ProcessInfo>) {
+    use std::{
+        os::unix::process::CommandExt,
+        process::{Command, Stdio},
+        thread,
+        time::Duration,
+    };
+
+    // the trailing `true`s stop the shells from `exec`ing their last command.
+    let root = Command::new("sh")
+        .args(["-c", "sh -c 'sleep 30; true' & sleep 30; true"])
+        .stdout(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .expect("sh should spawn");
+
+    let mut children = Vec::new();
+    for _ in 0..50 {
+        children = get_children(root.id()).expect("processes should be listed");
+        if children.len() >= 3 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    (root, children)
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -64,4 +106,36 @@ mod tests {
         assert!(!children.is_empty());
         assert!(children.iter().all(|e| e.parent_pid == 4));
     }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn get_nested_children() {
+        let (mut root, children) = super::spawn_test_tree();
+        let root_pid = root.id();
+
+        dbg!(&children);
+
+        for child in &children {
+            unsafe { libc::kill(child.pid.cast_signed(), libc::SIGKILL) };
+        }
+        let _ = root.kill();
+        let _ = root.wait();
+
+        // sh -> (sh -> sleep), sleep
+        assert_eq!(children.len(), 3);
+        assert!(children.iter().all(|e| e.pid != root_pid));
+
+        let direct: Vec<_> = children
+            .iter()
+            .filter(|e| e.parent_pid == root_pid)
+            .collect();
+        assert_eq!(direct.len(), 2);
+
+        let nested_sh = direct.iter().find(|e| e.name != "sleep").unwrap();
+        let grandchild = children
+            .iter()
+            .find(|e| e.parent_pid == nested_sh.pid)
+            .unwrap();
+        assert_eq!(grandchild.name, "sleep");
+    }
 }
@@ -0,0 +1,91 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use anyhow::{Context, anyhow};
+
+use crate::ProcessInfo;
+
+pub(crate) fn get_processes() -> anyhow::Result<Vec<ProcessInfo>> {
+    let mut processes = Vec::new();
+
+    for entry in fs::read_dir("/proc").context("reading /proc")? {
+        let entry = entry.context("reading /proc entry")?;
+
+        // only the numeric entries are processes.
+        let Some(pid) = entry.file_name().to_str().and_then(|p| p.parse().ok()) else {
+            continue;
+        };
+
+        match read_process(&entry.path(), pid) {
+            Ok(process) => processes.push(process),
+            // the process exited while we were looking at it.
+            Err(err) if err.downcast_ref::<std::io::Error>().is_some_and(is_gone) => {}
+            Err(err) => return Err(err.context(format!("reading process {pid}"))),
+        }
+    }
+
+    Ok(processes)
+}
+
+fn is_gone(err: &std::io::Error) -> bool {
+    // `ESRCH` is returned when the process dies between opening and reading the file.
+    err.kind() == ErrorKind::NotFound || err.raw_os_error() == Some(libc::ESRCH)
+}
+
+fn read_process(proc_dir: &Path, pid: u32) -> anyhow::Result<ProcessInfo> {
+    let stat = fs::read_to_string(proc_dir.join("stat"))?;
+    let (comm, parent_pid) = parse_stat(&stat)?;
+
+    // `comm` is truncated to 15 bytes, so prefer the name of the executable if we can see it.
+    // the link is unreadable for kernel threads and other users' processes.
+    let name = fs::read_link(proc_dir.join("exe"))
+        .ok()
+        .and_then(|exe| exe.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or(comm);
+
+    Ok(ProcessInfo {
+        name,
+        pid,
+        parent_pid,
+    })
+}
+
+/// parses `(comm, ppid)` out of `/proc/[pid]/stat`.
+///
+/// the format is `pid (comm) state ppid ...`, where `comm` may itself contain spaces and parentheses.
+fn parse_stat(stat: &str) -> anyhow::Result<(String, u32)> {
+    let open = stat.find('(').ok_or(anyhow!("stat missing `(`"))?;
+    let close = stat.rfind(')').ok_or(anyhow!("stat missing `)`"))?;
+    if close < open {
+        return Err(anyhow!("stat has mismatched parentheses"));
+    }
+
+    let comm = stat[open + 1..close].to_string();
+    let parent_pid = stat[close + 1..]
+        .split_whitespace()
+        .nth(1)
+        .ok_or(anyhow!("stat missing ppid"))?
+        .parse()
+        .context("parsing ppid")?;
+
+    Ok((comm, parent_pid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_stat;
+
+    #[test]
+    fn parse_stat_comm() {
+        assert_eq!(
+            parse_stat("1234 (bash) S 1000 1234 1234 0 -1").unwrap(),
+            ("bash".to_string(), 1000)
+        );
+        // `comm` can contain anything, including parentheses.
+        assert_eq!(
+            parse_stat("42 (a) b (c)) R 7 42 42 0 -1").unwrap(),
+            ("a) b (c)".to_string(), 7)
+        );
+        assert!(parse_stat("42 bash S 7").is_err());
+        assert!(parse_stat("42 (bash) S").is_err());
+    }
+}
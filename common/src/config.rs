@@ -0,0 +1,190 @@
+//! loading the helper's and runner's configs, from a toml file and the environment, and swapping them on reload.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env,
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock, RwLock},
+};
+
+use serde::de::DeserializeOwned;
+
+/// a config file, before environment variables and defaults.
+pub trait FileConfig: Default + DeserializeOwned {
+    /// what the file is validated into.
+    type Config;
+
+    /// sets the fields that have an environment variable from it, with [`env_var`] or [`var`].
+    fn apply_env(&mut self, problems: &mut Vec<String>);
+
+    fn validate(self, problems: &mut Vec<String>) -> Self::Config;
+
+    /// the settings that differ between `old` and `new` but are only read at startup.
+    fn unreloadable(old: &Self::Config, new: &Self::Config) -> Vec<String>;
+}
+
+/// the old and new config, after a [`Store::reload`].
+pub type Reloaded<C> = (Arc<C>, Arc<C>);
+
+/// holds the current config, which may be swapped by [`Store::reload`].
+pub struct Store<F: FileConfig> {
+    current: OnceLock<RwLock<Arc<F::Config>>>,
+    /// where the config was loaded from, and whether it must exist.
+    source: OnceLock<(PathBuf, bool)>,
+}
+
+impl<F: FileConfig> Store<F> {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            current: OnceLock::new(),
+            source: OnceLock::new(),
+        }
+    }
+
+    pub fn get(&self) -> Arc<F::Config> {
+        self.current
+            .get()
+            .expect("config is loaded at startup")
+            .read()
+            .expect("not poisoned")
+            .clone()
+    }
+
+    pub fn init(&self, path: PathBuf, required: bool, config: F::Config) {
+        self.current
+            .set(RwLock::new(Arc::new(config)))
+            .unwrap_or_else(|_| panic!("config is only loaded once"));
+        self.source
+            .set((path, required))
+            .expect("config is only loaded once");
+    }
+
+    /// loads the config again from where it was loaded at startup, keeping the old config if there are any problems.
+    ///
+    /// # Errors
+    /// every problem with the new config, including settings that can only be changed by restarting.
+    pub fn reload(&self) -> Result<Reloaded<F::Config>, Vec<String>> {
+        let (path, required) = self.source.get().expect("config is loaded at startup");
+        let new = Arc::new(load::<F>(path, *required)?);
+
+        let mut current = self
+            .current
+            .get()
+            .expect("config is loaded at startup")
+            .write()
+            .expect("not poisoned");
+
+        let problems = F::unreloadable(&current, &new);
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        let old = std::mem::replace(&mut *current, new.clone());
+        Ok((old, new))
+    }
+}
+
+impl<F: FileConfig> Default for Store<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// reads the config at `path`, then applies the environment variables, returning every problem found.
+///
+/// a missing file is only a problem if it is `required`.
+///
+/// # Errors
+/// if the file can't be read or parsed, or the config has problems.
+pub fn load<F: FileConfig>(path: &Path, required: bool) -> Result<F::Config, Vec<String>> {
+    let mut problems = Vec::new();
+
+    let mut file = match fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents).unwrap_or_else(|err| {
+            problems.push(format!("{}: {err}", path.display()));
+            F::default()
+        }),
+        Err(err) if err.kind() == ErrorKind::NotFound && !required => F::default(),
+        Err(err) => {
+            problems.push(format!("{}: {err}", path.display()));
+            F::default()
+        }
+    };
+
+    read_dotenv(&mut problems);
+    file.apply_env(&mut problems);
+    let config = file.validate(&mut problems);
+
+    if problems.is_empty() {
+        Ok(config)
+    } else {
+        Err(problems)
+    }
+}
+
+/// the environment variables the process was started with, which `.env` does not override.
+static STARTED_WITH: OnceLock<BTreeSet<String>> = OnceLock::new();
+/// what `.env` had at the last [`load`].
+static DOTENV: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// loads `.env` into the environment, noting which variables were set before it.
+///
+/// the config only takes those from the environment, and reads the rest from `.env` again on every [`load`], so a
+/// reload picks up changes to it.
+pub fn load_dotenv() {
+    STARTED_WITH.get_or_init(|| {
+        env::vars_os()
+            .filter_map(|(var, _)| var.into_string().ok())
+            .collect()
+    });
+
+    if let Err(err) = dotenvy::dotenv() {
+        eprintln!("could not load .env: {err}");
+    }
+}
+
+/// reads `.env` again for [`var`].
+fn read_dotenv(problems: &mut Vec<String>) {
+    let vars = match dotenvy::dotenv_iter() {
+        Ok(vars) => vars.collect(),
+        Err(err) if err.not_found() => Ok(BTreeMap::new()),
+        Err(err) => Err(err),
+    };
+    let vars = vars.unwrap_or_else(|err| {
+        problems.push(format!(".env: {err}"));
+        BTreeMap::new()
+    });
+
+    *DOTENV.lock().expect("not poisoned") = vars;
+}
+
+/// the environment variable `var`, from `.env` as of the last [`load`] if the process was not started with it.
+#[must_use]
+pub fn var(var: &str) -> Option<String> {
+    match STARTED_WITH.get() {
+        Some(started_with) if !started_with.contains(var) => {
+            DOTENV.lock().expect("not poisoned").get(var).cloned()
+        }
+        _ => env::var(var).ok(),
+    }
+}
+
+/// sets `field` from the environment variable `var`, if it is set.
+pub fn env_var<T: FromStr>(var: &str, field: &mut Option<T>, problems: &mut Vec<String>)
+where
+    T::Err: Display,
+{
+    let Some(value) = self::var(var) else {
+        return;
+    };
+
+    match value.parse() {
+        Ok(value) => *field = Some(value),
+        Err(err) => problems.push(format!("`{var}` ({value:?}): {err}")),
+    }
+}
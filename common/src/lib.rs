@@ -1,5 +1,12 @@
+use std::{
+    fmt::Display,
+    time::{Duration, SystemTime},
+};
+
 use serde::{Deserialize, Serialize};
 
+pub mod config;
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Stats {
     /// usage per cpu core.
@@ -15,3 +22,292 @@ pub struct Stats {
     // bytes written + read since last refresh.
     pub server_disk_usage: Option<u64>,
 }
+
+/// the lifecycle state of the game server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerState {
+    /// not running, either never started or stopped on request.
+    #[default]
+    Stopped,
+    /// the server is being started, and may not be joinable yet.
+    Starting,
+    /// the server is running and joinable.
+    Ready,
+    /// the server was requested to stop and has not exited yet.
+    Stopping,
+    /// the server exited without being requested to.
+    Crashed,
+}
+
+impl ServerState {
+    /// whether the server's process is (or is about to be) alive.
+    #[must_use]
+    pub const fn is_running(self) -> bool {
+        matches!(self, Self::Starting | Self::Ready | Self::Stopping)
+    }
+
+    /// whether the server may go from `self` to `next`.
+    #[must_use]
+    pub const fn can_become(self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (Self::Stopped | Self::Crashed, Self::Starting)
+                | (
+                    Self::Starting,
+                    Self::Ready | Self::Stopping | Self::Stopped | Self::Crashed
+                )
+                | (Self::Ready, Self::Stopping | Self::Stopped | Self::Crashed)
+                | (Self::Stopping, Self::Stopped)
+        )
+    }
+}
+
+/// a change of the game server's [`ServerState`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: ServerState,
+    pub to: ServerState,
+    pub at: SystemTime,
+}
+
+/// how far along the game server is in starting up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Progress {
+    /// 0 to 100.
+    pub percent: u8,
+    /// what the server is doing right now.
+    pub stage: String,
+}
+
+impl Progress {
+    #[must_use]
+    pub fn new(percent: u8, stage: impl Into<String>) -> Self {
+        Self {
+            percent: percent.min(100),
+            stage: stage.into(),
+        }
+    }
+}
+
+/// the game server's state, sent whenever it or its startup [`Progress`] changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateUpdate {
+    pub state: ServerState,
+    pub since: SystemTime,
+    /// only set while the server is [`ServerState::Starting`].
+    pub progress: Option<Progress>,
+}
+
+/// what an instance's game server supports, so clients can hide what won't work.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Capabilities {
+    /// listing the online players with `/list`.
+    pub list: bool,
+    /// running console commands with `/exec`.
+    pub exec: bool,
+    /// listing the installed mods or plugins in `/info`.
+    pub mods: bool,
+    /// backing up the world.
+    pub backups: bool,
+    /// restarting with `/restart`.
+    pub restart: bool,
+    /// reading information about the world.
+    pub world_info: bool,
+}
+
+/// one of the game servers a runner runs, listed at `/instances`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceSummary {
+    /// used in the routes, eg. `/instances/{id}/start`.
+    pub id: String,
+    /// eg. `minecraft`.
+    pub server_type: String,
+    pub state: ServerState,
+    /// whether it is served at the routes without `/instances/{id}`.
+    pub default: bool,
+}
+
+/// who may use a helper route, or see a console line, from least to most trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthLevel {
+    /// anyone who can reach the helper.
+    Unauthed,
+    /// has the helper's `basic_token`.
+    Basic,
+    /// has the helper's `stop_token`.
+    Stop,
+}
+
+impl AuthLevel {
+    pub const ALL: [Self; 3] = [Self::Unauthed, Self::Basic, Self::Stop];
+}
+
+/// a console line as each [`AuthLevel`] sees it, `None` if it is hidden from them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsoleLine {
+    pub unauthed: Option<String>,
+    pub basic: Option<String>,
+    pub stop: Option<String>,
+}
+
+impl ConsoleLine {
+    #[must_use]
+    pub fn get(&self, level: AuthLevel) -> Option<&str> {
+        match level {
+            AuthLevel::Unauthed => self.unauthed.as_deref(),
+            AuthLevel::Basic => self.basic.as_deref(),
+            AuthLevel::Stop => self.stop.as_deref(),
+        }
+    }
+
+    pub fn set(&mut self, level: AuthLevel, line: Option<String>) {
+        match level {
+            AuthLevel::Unauthed => self.unauthed = line,
+            AuthLevel::Basic => self.basic = line,
+            AuthLevel::Stop => self.stop = line,
+        }
+    }
+}
+
+/// compares tokens in constant time, so a token can't be guessed from how long a rejection takes.
+#[must_use]
+pub fn same_token(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// the version of the link protocol, in the first byte of every frame.
+///
+/// bumped on any change to [`HelperMessage`] or [`RunnerMessage`], since bitcode is not self-describing.
+pub const LINK_VERSION: u8 = 5;
+
+/// how often the helper sends a [`HelperMessage::Ping`] over the link.
+pub const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+/// how long either side of the link waits for a frame from the other before closing it, so a link that died without
+/// closing is noticed.
+pub const LINK_IDLE_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// sent by the helper to a runner over their link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HelperMessage {
+    /// a `GET` of `path`, which may have a query, answered by a [`RunnerMessage::Response`] with the same `id`.
+    Request { id: u64, path: String },
+    /// answered by a [`RunnerMessage::Pong`], so both sides hear from each other at least every [`PING_INTERVAL`].
+    Ping,
+}
+
+/// sent by a runner to the helper over their link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RunnerMessage {
+    Response {
+        id: u64,
+        status: u16,
+        content_type: Option<String>,
+        body: Vec<u8>,
+    },
+    /// sent first when the link opens, with the instance served at the routes without `/instances/{id}`.
+    Linked {
+        default_instance: Option<String>,
+    },
+    Stats {
+        instance: String,
+        stats: Stats,
+    },
+    /// a console line, filtered for each auth level.
+    Console {
+        instance: String,
+        line: ConsoleLine,
+    },
+    /// sent for every instance when the link opens, then whenever one's state changes.
+    State {
+        instance: String,
+        update: StateUpdate,
+    },
+    Pong,
+}
+
+/// encodes `message` as one frame of the link, to be sent as a binary websocket message.
+///
+/// # Errors
+/// if bitcode fails to serialize it.
+pub fn encode_frame<T: Serialize>(message: &T) -> Result<Vec<u8>, bitcode::Error> {
+    let mut frame = vec![LINK_VERSION];
+    frame.extend(bitcode::serialize(message)?);
+    Ok(frame)
+}
+
+/// decodes a frame of the link made by [`encode_frame`].
+///
+/// # Errors
+/// if the frame is from another version of the protocol, or is malformed.
+pub fn decode_frame<T: for<'de> Deserialize<'de>>(frame: &[u8]) -> Result<T, FrameError> {
+    match frame.split_first() {
+        Some((&LINK_VERSION, message)) => bitcode::deserialize(message).map_err(FrameError::Decode),
+        Some((&version, _)) => Err(FrameError::Version(version)),
+        None => Err(FrameError::Empty),
+    }
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    Empty,
+    /// the other side speaks this version of the protocol.
+    Version(u8),
+    Decode(bitcode::Error),
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty frame"),
+            Self::Version(version) => write!(
+                f,
+                "link protocol version {version} is not ours ({LINK_VERSION}), update the helper and runner together"
+            ),
+            Self::Decode(err) => write!(f, "malformed frame: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameError, HelperMessage, LINK_VERSION, decode_frame, encode_frame, same_token};
+
+    #[test]
+    fn tokens() {
+        assert!(same_token(b"secret", b"secret"));
+        assert!(!same_token(b"secret", b"secreT"));
+        assert!(!same_token(b"secret", b"secret2"));
+        assert!(!same_token(b"", b"secret"));
+    }
+
+    #[test]
+    fn frames() {
+        let request = HelperMessage::Request {
+            id: 7,
+            path: "/running".to_string(),
+        };
+        let mut frame = encode_frame(&request).unwrap();
+        assert_eq!(frame[0], LINK_VERSION);
+
+        let Ok(HelperMessage::Request { id, path }) = decode_frame(&frame) else {
+            panic!("not a request");
+        };
+        assert_eq!((id, path.as_str()), (7, "/running"));
+
+        frame[0] = LINK_VERSION + 1;
+        assert!(matches!(
+            decode_frame::<HelperMessage>(&frame),
+            Err(FrameError::Version(_))
+        ));
+        assert!(matches!(
+            decode_frame::<HelperMessage>(&[]),
+            Err(FrameError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::Request,
+    handler::Handler,
+    http::{HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{self, MethodRouter},
+};
+use common::{AuthLevel, same_token};
+
+use crate::config::{Config, config};
+
+/// how long a ticket can be used for after it is made.
+const TICKET_TTL: Duration = Duration::from_secs(30);
+
+/// unused tickets, and the auth level and expiry of each.
+static TICKETS: LazyLock<Mutex<HashMap<String, (AuthLevel, Instant)>>> =
+    LazyLock::new(Mutex::default);
+
+/// a `GET` route needing `route`'s auth level in the config.
+///
+/// the caller's [`AuthLevel`] is added to the request's extensions, for routes that serve each level differently.
+pub fn get<H, T, S>(route: &'static str, handler: H) -> MethodRouter<S>
+where
+    H: Handler<T, S>,
+    T: 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    assert!(
+        config().route_auth.contains_key(route),
+        "{route} is not in `ROUTE_AUTH`"
+    );
+
+    routing::get(handler).route_layer(middleware::from_fn(move |req: Request, next: Next| {
+        require(route, req, next)
+    }))
+}
+
+/// a `GET` route anyone can reach, whose handler checks the caller's [`AuthLevel`] in the request's extensions.
+pub fn get_any<H, T, S>(handler: H) -> MethodRouter<S>
+where
+    H: Handler<T, S>,
+    T: 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    routing::get(handler).route_layer(middleware::from_fn(
+        |mut req: Request, next: Next| async move {
+            let level = level(&config(), req.headers(), req.uri().query());
+            req.extensions_mut().insert(level);
+            next.run(req).await
+        },
+    ))
+}
+
+async fn require(route: &'static str, mut req: Request, next: Next) -> Response {
+    let config = config();
+    let level = level(&config, req.headers(), req.uri().query());
+    if level >= config.auth_level(route) {
+        req.extensions_mut().insert(level);
+        next.run(req).await
+    } else {
+        StatusCode::UNAUTHORIZED.into_response()
+    }
+}
+
+/// the auth level of a request, from its `token` header, or `ticket` query for websockets.
+fn level(config: &Config, headers: &HeaderMap, query: Option<&str>) -> AuthLevel {
+    if let Some(token) = headers.get("token") {
+        if same_token(token.as_bytes(), config.stop_token.as_bytes()) {
+            return AuthLevel::Stop;
+        }
+        if same_token(token.as_bytes(), config.basic_token.as_bytes()) {
+            return AuthLevel::Basic;
+        }
+    }
+
+    let ticket = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("ticket="));
+    let Some(ticket) = ticket else {
+        return AuthLevel::Unauthed;
+    };
+
+    match TICKETS.lock().expect("not poisoned").remove(ticket) {
+        Some((level, expires)) if Instant::now() < expires => level,
+        _ => AuthLevel::Unauthed,
+    }
+}
+
+/// makes a single-use ticket with the caller's auth level, for a `?ticket=` query.
+///
+/// browsers can't set the `token` header when opening a websocket, so they get a ticket first.
+pub async fn ticket(headers: HeaderMap) -> Result<String, StatusCode> {
+    let level = level(&config(), &headers, None);
+    if level == AuthLevel::Unauthed {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let ticket = rand::random::<[u8; 16]>()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+
+    let mut tickets = TICKETS.lock().expect("not poisoned");
+    let now = Instant::now();
+    tickets.retain(|_, (_, expires)| now < *expires);
+    tickets.insert(ticket.clone(), (level, now + TICKET_TTL));
+
+    Ok(ticket)
+}
@@ -1,26 +1,56 @@
 // use rocket::{State, futures::SinkExt, get, tokio::sync::broadcast};
 // use rocket_ws::{Channel, WebSocket};
 
+use std::sync::Arc;
+
 use axum::{
+    Extension,
     extract::{
-        State, WebSocketUpgrade,
+        Query, State, WebSocketUpgrade,
         ws::{Message, WebSocket},
     },
     response::Response,
 };
-use tokio::sync::broadcast::Receiver;
+use common::{AuthLevel, ConsoleLine};
+use tokio::sync::broadcast::{Receiver, error::RecvError};
 
-use super::AppState;
+use super::{AppState, InstanceQuery};
 
-/// forward the websocket from the local runner.
-pub async fn console(ws: WebSocketUpgrade, State(state): AppState) -> Response {
+/// forward the console of one of the runner's instances, as it was filtered for the caller's auth level.
+pub async fn console(
+    ws: WebSocketUpgrade,
+    State(state): AppState,
+    Query(InstanceQuery { instance }): Query<InstanceQuery>,
+    Extension(level): Extension<AuthLevel>,
+) -> Response {
     let channel = state.console.subscribe();
-    ws.on_upgrade(move |socket| handle_socket(socket, channel))
+    ws.on_upgrade(move |socket| handle_socket(socket, channel, state, instance, level))
 }
 
-async fn handle_socket(mut socket: WebSocket, mut channel: Receiver<String>) {
-    while let Ok(message) = channel.recv().await {
-        if let Err(err) = socket.send(Message::Text(message.into())).await {
+async fn handle_socket(
+    mut socket: WebSocket,
+    mut channel: Receiver<(String, ConsoleLine)>,
+    state: Arc<crate::AppState>,
+    asked: Option<String>,
+    level: AuthLevel,
+) {
+    loop {
+        let (instance, line) = match channel.recv().await {
+            Ok(line) => line,
+            Err(RecvError::Lagged(lag)) => {
+                tracing::debug!("channel lagged {lag} msgs");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        if state.instance(asked.as_deref()).as_ref() != Some(&instance) {
+            continue;
+        }
+        // dropped for this level.
+        let Some(line) = line.get(level) else {
+            continue;
+        };
+        if let Err(err) = socket.send(Message::text(line)).await {
             tracing::debug!("{err}, closing socket");
             break;
         }
@@ -0,0 +1,36 @@
+use axum::extract::{Path, Query, State};
+use reqwest::Url;
+
+use super::{
+    AppState, InstanceQuery,
+    make_forward::{Error, Forwarded, forward},
+};
+
+/// forward the crash report list from the runner.
+pub async fn list(
+    State(state): AppState,
+    Query(InstanceQuery { instance }): Query<InstanceQuery>,
+) -> Result<Forwarded, Error> {
+    forward(&state, "crashes", instance.as_deref(), None).await
+}
+
+/// forward a crash report file from the runner, as bytes since crash logs are not always valid utf-8.
+pub async fn download(
+    Path((id, file)): Path<(String, String)>,
+    State(state): AppState,
+    Query(InstanceQuery { instance }): Query<InstanceQuery>,
+) -> Result<Forwarded, Error> {
+    // encoded again, since axum decoded them.
+    let mut path = Url::parse("http://runner/crashes").expect("valid url");
+    path.path_segments_mut()
+        .expect("an http url has a path")
+        .push(&id)
+        .push(&file);
+    forward(
+        &state,
+        path.path().trim_start_matches('/'),
+        instance.as_deref(),
+        None,
+    )
+    .await
+}
@@ -0,0 +1,100 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{
+    Extension,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use common::AuthLevel;
+use reqwest::Url;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+
+use super::{AppState, InstanceQuery, make_forward::forward};
+use crate::config::config;
+
+/// one line of the exec log.
+#[derive(Serialize)]
+struct Record<'a> {
+    /// seconds since the unix epoch.
+    at: u64,
+    runner: &'a str,
+    level: AuthLevel,
+    command: &'a str,
+    /// the runner's response, `None` if the request couldn't be forwarded.
+    status: Option<u16>,
+}
+
+/// forward a console command to the runner, if the caller may run it.
+pub async fn exec(
+    Path(cmd): Path<String>,
+    State(state): AppState,
+    Query(InstanceQuery { instance }): Query<InstanceQuery>,
+    Extension(level): Extension<AuthLevel>,
+) -> Response {
+    // a newline would let an allowed command smuggle in another.
+    if cmd.contains(['\n', '\r']) {
+        return (StatusCode::BAD_REQUEST, "commands can't have newlines").into_response();
+    }
+
+    let config = config();
+    if !config.may_exec(level, &cmd) {
+        tracing::info!(runner = state.id, ?level, "refused to run {cmd:?}");
+        let status = if level == AuthLevel::Unauthed {
+            StatusCode::UNAUTHORIZED
+        } else {
+            StatusCode::FORBIDDEN
+        };
+        return (status, "you may not run that command").into_response();
+    }
+
+    let mut path = Url::parse("http://runner/exec").expect("valid url");
+    path.path_segments_mut()
+        .expect("an http url has a path")
+        .push(&cmd);
+    let response = forward(
+        &state,
+        path.path().trim_start_matches('/'),
+        instance.as_deref(),
+        None,
+    )
+    .await;
+
+    tracing::info!(runner = state.id, ?level, "ran {cmd:?}");
+    let record = Record {
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        runner: &state.id,
+        level,
+        command: &cmd,
+        status: response
+            .as_ref()
+            .ok()
+            .map(|response| response.status.as_u16()),
+    };
+    if let Err(err) = append(&config.exec.log, &record).await {
+        tracing::error!(
+            "could not record {cmd:?} in {}: {err}",
+            config.exec.log.display()
+        );
+    }
+
+    response.into_response()
+}
+
+async fn append(log: &std::path::Path, record: &Record<'_>) -> anyhow::Result<()> {
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log)
+        .await?
+        .write_all(&line)
+        .await?;
+    Ok(())
+}
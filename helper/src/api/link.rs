@@ -0,0 +1,56 @@
+use std::future;
+
+use axum::{
+    extract::{
+        Path, WebSocketUpgrade,
+        ws::{Message, WebSocket},
+    },
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use common::same_token;
+use futures_util::{SinkExt, StreamExt};
+
+use crate::{AppState, config::Connection, runners};
+
+/// where a runner in reverse connection mode links to us, with its `link_token` as the `token` header.
+pub async fn link(
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Result<Response, (StatusCode, &'static str)> {
+    let Some(runner) = runners::get(&id) else {
+        return Err((StatusCode::NOT_FOUND, "no such runner"));
+    };
+    let Connection::Reverse { token } = &runner.config().connection else {
+        return Err((StatusCode::NOT_FOUND, "runner does not link to the helper"));
+    };
+
+    if headers
+        .get("token")
+        .is_none_or(|given| !same_token(given.as_bytes(), token.as_bytes()))
+    {
+        return Err((StatusCode::UNAUTHORIZED, "wrong link token"));
+    }
+
+    Ok(ws.on_upgrade(move |socket| serve(runner.clone(), socket)))
+}
+
+async fn serve(runner: std::sync::Arc<AppState>, socket: WebSocket) {
+    let (sink, stream) = socket.split();
+    let sink = sink
+        .with(|frame: Vec<u8>| future::ready(Ok::<_, axum::Error>(Message::Binary(frame.into()))));
+    let stream = stream.filter_map(|message| {
+        future::ready(match message {
+            Ok(Message::Binary(frame)) => Some(Some(frame.to_vec())),
+            Ok(Message::Close(_)) => Some(None),
+            Ok(_) => None,
+            Err(err) => {
+                tracing::warn!("link closed: {err}");
+                Some(None)
+            }
+        })
+    });
+
+    crate::link::serve(runner, sink, stream).await;
+}
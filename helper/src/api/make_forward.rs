@@ -1,4 +1,11 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use axum::{
+    body::Bytes,
+    http::{HeaderValue, StatusCode, header},
+    response::IntoResponse,
+};
+use reqwest::Url;
+
+use crate::AppState;
 
 pub struct Error;
 
@@ -13,30 +20,120 @@ impl IntoResponse for Error {
     }
 }
 
+/// a runner's response to a forwarded request.
+pub struct Forwarded {
+    pub status: StatusCode,
+    pub content_type: Option<HeaderValue>,
+    pub body: Bytes,
+}
+
+impl IntoResponse for Forwarded {
+    fn into_response(self) -> axum::response::Response {
+        let mut resp = (self.status, self.body).into_response();
+        if let Some(content_type) = self.content_type {
+            resp.headers_mut()
+                .insert(header::CONTENT_TYPE, content_type);
+        }
+        resp
+    }
+}
+
+/// requests `route` from the runner over its link, from `instance`'s routes if set, else from its default instance's.
+///
+/// `query` is forwarded without `instance`, which the caller parsed from it as an [`super::InstanceQuery`].
+pub async fn forward(
+    state: &AppState,
+    route: &str,
+    instance: Option<&str>,
+    query: Option<&str>,
+) -> Result<Forwarded, Error> {
+    let link = state.link().ok_or(Error)?;
+    link.get(runner_path(route, instance, query))
+        .await
+        .ok_or(Error)
+}
+
+/// the path of `route` on the runner, with `query` but without its `instance`.
+fn runner_path(route: &str, instance: Option<&str>, query: Option<&str>) -> String {
+    let mut path = match instance {
+        Some(instance) => {
+            let mut url = Url::parse("http://runner/instances").expect("valid url");
+            url.path_segments_mut()
+                .expect("an http url has a path")
+                .push(instance);
+            format!("{}/{route}", url.path())
+        }
+        None => format!("/{route}"),
+    };
+    let query: Vec<_> = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .filter(|pair| *pair != "instance" && !pair.starts_with("instance="))
+        .collect();
+    if !query.is_empty() {
+        path.push('?');
+        path.push_str(&query.join("&"));
+    }
+    path
+}
+
 // $route is required because the #[get] macro expects a literal, meaning i cant use stringify!($route) or any macro in its input.
 macro_rules! make_forward {
     ($name:ident, $route:expr) => {
+        make_forward!(@forward $name, true);
+    };
+    // a route of the runner itself, rather than of one of its instances.
+    ($name:ident, $route:expr, runner) => {
+        make_forward!(@forward $name, false);
+    };
+    (@forward $name:ident, $per_instance:expr) => {
         pub mod $name {
-            use axum::http::StatusCode;
-            use helper::UrlExt;
+            use axum::{
+                extract::{Query, RawQuery, State},
+                http::StatusCode,
+            };
 
-            use super::{AppState, make_forward::Error};
-            use crate::RUNNER_ADDR;
+            use super::{
+                AppState, InstanceQuery,
+                make_forward::{Error, forward},
+            };
 
             pub async fn $name(
-                axum::extract::State(state): AppState,
+                State(state): AppState,
+                Query(InstanceQuery { instance }): Query<InstanceQuery>,
+                RawQuery(query): RawQuery,
             ) -> Result<(StatusCode, String), Error> {
-                let $name = state
-                    .client
-                    .get(RUNNER_ADDR.join_unchecked(stringify!($name)))
-                    .send()
-                    .await
-                    .map_err(|_| Error)?;
-                let status = StatusCode::from_u16($name.status().as_u16()).map_err(|_| Error)?;
-                let $name = $name.text().await.map_err(|_| Error)?;
-
-                Ok((status, $name))
+                let instance = if $per_instance { instance } else { None };
+                let $name = forward(
+                    &state,
+                    stringify!($name),
+                    instance.as_deref(),
+                    query.as_deref(),
+                )
+                .await?;
+                let body = String::from_utf8_lossy(&$name.body).into_owned();
+
+                Ok(($name.status, body))
             }
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::runner_path;
+
+    #[test]
+    fn paths() {
+        assert_eq!(runner_path("start", None, None), "/start");
+        assert_eq!(
+            runner_path("restart", Some("mc"), Some("instance=mc&delay=5")),
+            "/instances/mc/restart?delay=5"
+        );
+        assert_eq!(
+            runner_path("exec/say%20hi", Some("a b"), Some("instance=a%20b")),
+            "/instances/a%20b/exec/say%20hi"
+        );
+        assert_eq!(runner_path("ping", None, Some("instance=mc")), "/ping");
+    }
+}
@@ -1,88 +1,92 @@
-use std::{env, sync::Arc};
-
-use axum::{
-    Router,
-    body::Body,
-    extract::Request,
-    http::{Response, StatusCode},
-    routing::get,
-};
-use tower_http::{
-    CompressionLevel, auth::AsyncRequireAuthorizationLayer, compression::CompressionLayer,
-};
+use std::sync::Arc;
 
+use axum::{Router, routing::get};
+use serde::Deserialize;
+use tower_http::{CompressionLevel, compression::CompressionLayer};
+
+mod auth;
 #[macro_use]
 mod make_forward;
+pub use make_forward::Forwarded;
 
 type AppState = axum::extract::State<Arc<crate::AppState>>;
 
-// basic
+/// `?instance=`, which of the runner's instances a route is for, its default one if unset.
+#[derive(Debug, Deserialize)]
+pub struct InstanceQuery {
+    instance: Option<String>,
+}
+
 make_forward!(start, "/start");
 make_forward!(ip, "/ip");
 make_forward!(info, "/info");
-
-// stop
 make_forward!(stop, "/stop");
+make_forward!(restart, "/restart");
 make_forward!(running, "/running");
-make_forward!(ping, "/ping");
+make_forward!(ping, "/ping", runner);
 make_forward!(list, "/list");
+make_forward!(progress, "/progress");
+make_forward!(last_exit, "/last_exit");
+make_forward!(capabilities, "/capabilities");
 
 pub mod stats;
 
 pub mod console;
 
-// stop
+pub mod state;
+
 pub mod wake;
 
-pub fn unauthed() -> Router<Arc<crate::AppState>> {
+pub mod crashes;
+
+pub mod exec;
+
+pub mod reload;
+
+mod link;
+
+mod runners;
+
+/// every route forwarded to one runner, nested at `/api/{runner}`.
+pub fn runner_routes() -> Router<Arc<crate::AppState>> {
     Router::new()
-        .route("/stats", get(stats::stats))
-        .route("/console", get(console::console))
-        .route("/running", get(running::running))
-        .route("/ping", get(ping::ping))
-        .route("/list", get(list::list))
+        .route("/stats", auth::get("stats", stats::stats))
+        .route("/console", auth::get("console", console::console))
+        .route("/state", auth::get("state", state::state))
+        .route("/running", auth::get("running", running::running))
+        .route("/ping", auth::get("ping", ping::ping))
+        .route("/list", auth::get("list", list::list))
+        .route("/progress", auth::get("progress", progress::progress))
+        .route("/last_exit", auth::get("last_exit", last_exit::last_exit))
+        .route(
+            "/capabilities",
+            auth::get("capabilities", capabilities::capabilities),
+        )
+        .route("/start", auth::get("start", start::start))
+        .route("/ip", auth::get("ip", ip::ip))
+        .route("/stop", auth::get("stop", stop::stop))
+        .route("/restart", auth::get("restart", restart::restart))
+        .route("/wake", auth::get("wake", wake::wake))
+        .route("/exec/{*cmd}", auth::get_any(exec::exec))
+        .route("/crashes", auth::get("crashes", crashes::list))
+        .route(
+            "/crashes/{id}/{file}",
+            auth::get("crashes", crashes::download),
+        )
         .merge(
             Router::new()
-                .route("/info", get(info::info))
+                .route("/info", auth::get("info", info::info))
                 .layer(CompressionLayer::new().quality(CompressionLevel::Precise(3))),
         )
 }
 
-macro_rules! require_auth {
-    ($token:expr) => {
-        AsyncRequireAuthorizationLayer::new(|req: Request| async move {
-            let unauth = Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .body(Body::empty())
-                .expect("should be able to create response");
-
-            let Some(token) = req.headers().get("token") else {
-                return Err(unauth);
-            };
-
-            if token == $token {
-                Ok(req)
-            } else {
-                Err(unauth)
-            }
-        })
-    };
-}
-
-pub fn basic_auth() -> Router<Arc<crate::AppState>> {
-    Router::new()
-        .route("/start", get(start::start))
-        .route("/ip", get(ip::ip))
-        .layer(require_auth!(
-            &env::var("BASIC_TOKEN").expect("no `BASIC_TOKEN` env var.")
-        ))
-}
-
-pub fn stop_auth() -> Router<Arc<crate::AppState>> {
+/// the routes for the helper itself.
+///
+/// runners can't have these ids, see `config::RESERVED_IDS`.
+pub fn helper_routes() -> Router {
     Router::new()
-        .route("/stop", get(stop::stop))
-        .route("/wake", get(wake::wake))
-        .layer(require_auth!(
-            &env::var("STOP_TOKEN").expect("no `STOP_TOKEN` env var.")
-        ))
+        .route("/runners", get(runners::runners))
+        .route("/ticket", get(auth::ticket))
+        .route("/reload", auth::get("reload", reload::reload))
+        .route("/link/{runner}", get(link::link))
 }
@@ -0,0 +1,47 @@
+use axum::http::StatusCode;
+use futures_util::future;
+
+use super::make_forward::forward;
+use crate::runners;
+
+/// reload the helper's config, then every runner's.
+pub async fn reload() -> (StatusCode, String) {
+    if let Err(problems) = runners::reload() {
+        tracing::warn!("not reloading config: {problems:?}");
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("helper not reloaded:\n- {}", problems.join("\n- ")),
+        );
+    }
+
+    // at once, so a few stalled links can't add up past the route timeout.
+    let reloads = runners::all().map(|runner| async move {
+        let reload = match forward(runner, "reload", None, None).await {
+            Ok(resp) => (
+                resp.status,
+                String::from_utf8_lossy(&resp.body).into_owned(),
+            ),
+            Err(_) => {
+                tracing::warn!("could not reload runner {}", runner.id);
+                (
+                    StatusCode::BAD_GATEWAY,
+                    "couldn't reach the runner".to_string(),
+                )
+            }
+        };
+        (runner, reload)
+    });
+    let reloads = future::join_all(reloads).await;
+
+    let mut status = StatusCode::OK;
+    let mut msg = "helper reloaded!".to_string();
+    for (runner, (runner_status, text)) in reloads {
+        // the first failure is the status of the whole reload.
+        if status.is_success() {
+            status = runner_status;
+        }
+        msg.push_str(&format!("\nrunner {}: {text}", runner.id));
+    }
+
+    (status, msg)
+}
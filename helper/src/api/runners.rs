@@ -0,0 +1,30 @@
+use axum::Json;
+use serde::Serialize;
+
+use crate::{config::config, runners as all};
+
+#[derive(Serialize)]
+pub struct Runner {
+    /// used in the routes, eg. `/api/{id}/start`.
+    id: String,
+    name: String,
+    /// whether it is served at the routes without `/api/{id}`.
+    default: bool,
+    /// whether its link is up, so its routes can be forwarded.
+    linked: bool,
+}
+
+/// lists every runner the helper fronts.
+pub async fn runners() -> Json<Vec<Runner>> {
+    let config = config();
+    let runners = all::all()
+        .map(|runner| Runner {
+            id: runner.id.clone(),
+            name: config.runners[&runner.id].name.clone(),
+            default: config.default_runner.as_ref() == Some(&runner.id),
+            linked: runner.link().is_some(),
+        })
+        .collect();
+
+    Json(runners)
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        Query, State, WebSocketUpgrade,
+        ws::{Message, WebSocket},
+    },
+    response::Response,
+};
+use tokio::sync::watch::Receiver;
+
+use super::{AppState, InstanceQuery};
+use crate::Instances;
+
+/// streams the state and startup progress of one of the runner's instances as json, starting with the current one.
+///
+/// `null` while the runner is not linked, or has no such instance.
+pub async fn state(
+    ws: WebSocketUpgrade,
+    State(state): AppState,
+    Query(InstanceQuery { instance }): Query<InstanceQuery>,
+) -> Response {
+    let channel = state.instances.subscribe();
+    ws.on_upgrade(move |socket| handle_socket(socket, channel, state, instance))
+}
+
+async fn handle_socket(
+    mut socket: WebSocket,
+    mut channel: Receiver<Option<Instances>>,
+    state: Arc<crate::AppState>,
+    asked: Option<String>,
+) {
+    // the channel changes with every instance's state, so only changes of this one's are sent.
+    let mut last = None;
+    loop {
+        // before borrowing, since finding the default instance borrows the channel too.
+        let instance = state.instance(asked.as_deref());
+        let update = channel
+            .borrow_and_update()
+            .as_ref()
+            .zip(instance)
+            .and_then(|(instances, instance)| instances.states.get(&instance).cloned());
+        let Ok(message) = serde_json::to_string(&update) else {
+            tracing::warn!("failed to serialize to json");
+            break;
+        };
+
+        if last.as_ref() != Some(&message) {
+            if let Err(err) = socket.send(Message::Text(message.clone().into())).await {
+                tracing::debug!("{err}, closing socket");
+                break;
+            }
+            last = Some(message);
+        }
+
+        if channel.changed().await.is_err() {
+            break;
+        }
+    }
+
+    tracing::debug!("ws closed");
+}
@@ -1,28 +1,45 @@
+use std::sync::Arc;
+
 use axum::{
     extract::{
-        State, WebSocketUpgrade,
+        Query, State, WebSocketUpgrade,
         ws::{Message, WebSocket},
     },
     response::Response,
 };
 use common::Stats;
-use reqwest_websocket::Bytes;
-use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::{Receiver, error::RecvError};
 
-use super::AppState;
+use super::{AppState, InstanceQuery};
 
-/// forward the websocket from the local runner.
-pub async fn stats(ws: WebSocketUpgrade, State(state): AppState) -> Response {
+/// forward the stats of one of the runner's instances.
+pub async fn stats(
+    ws: WebSocketUpgrade,
+    State(state): AppState,
+    Query(InstanceQuery { instance }): Query<InstanceQuery>,
+) -> Response {
     let channel = state.stats.subscribe();
-    ws.on_upgrade(move |socket| handle_socket(socket, channel))
+    ws.on_upgrade(move |socket| handle_socket(socket, channel, state, instance))
 }
 
-async fn handle_socket(mut socket: WebSocket, mut channel: Receiver<Bytes>) {
-    while let Ok(message) = channel.recv().await {
-        let Ok(stats) = bitcode::deserialize::<Stats>(&message) else {
-            tracing::warn!("failed to deserialize bitcode");
-            continue;
+async fn handle_socket(
+    mut socket: WebSocket,
+    mut channel: Receiver<(String, Stats)>,
+    state: Arc<crate::AppState>,
+    asked: Option<String>,
+) {
+    loop {
+        let (instance, stats) = match channel.recv().await {
+            Ok(stats) => stats,
+            Err(RecvError::Lagged(lag)) => {
+                tracing::debug!("channel lagged {lag} msgs");
+                continue;
+            }
+            Err(RecvError::Closed) => break,
         };
+        if state.instance(asked.as_deref()).as_ref() != Some(&instance) {
+            continue;
+        }
         let Ok(message) = serde_json::to_string(&stats) else {
             tracing::warn!("failed to serialize to json");
             continue;
@@ -0,0 +1,611 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use common::{
+    AuthLevel,
+    config::{Reloaded, Store, env_var},
+};
+use reqwest::Url;
+use serde::Deserialize;
+
+/// the config file used if `--config` and `HELPER_CONFIG` are not set.
+pub const DEFAULT_PATH: &str = "helper.toml";
+
+static CONFIG: Store<FileConfig> = Store::new();
+
+/// the current config, which may be swapped by [`reload`].
+pub fn config() -> Arc<Config> {
+    CONFIG.get()
+}
+
+pub fn init(path: PathBuf, required: bool, config: Config) {
+    CONFIG.init(path, required, config);
+}
+
+/// see [`Store::reload`], which returns the old and new config.
+pub fn reload() -> Result<Reloaded<Config>, Vec<String>> {
+    CONFIG.reload()
+}
+
+/// see [`common::config::load`].
+pub fn load(path: &Path, required: bool) -> Result<Config, Vec<String>> {
+    common::config::load::<FileConfig>(path, required)
+}
+
+#[derive(Debug)]
+pub struct Config {
+    /// keyed by id, which is used in the routes.
+    pub runners: BTreeMap<String, Arc<RunnerConfig>>,
+    /// the runner served at the routes without `/api/{runner}`.
+    pub default_runner: Option<String>,
+    pub basic_token: String,
+    pub stop_token: String,
+    pub port: u16,
+    /// every route in [`ROUTE_AUTH`], with `[auth]` applied.
+    pub route_auth: BTreeMap<&'static str, AuthLevel>,
+    pub exec: ExecConfig,
+}
+
+/// who may run which console commands, besides those with `exec`'s auth level, who may run any.
+#[derive(Debug)]
+pub struct ExecConfig {
+    /// the commands each level may run, eg. `say` for `say hi`, which every level above it may run too.
+    pub allowed: BTreeMap<AuthLevel, Vec<String>>,
+    /// every command run is appended to it.
+    pub log: PathBuf,
+}
+
+impl Config {
+    /// the auth level needed to use `route`, one of [`ROUTE_AUTH`].
+    pub fn auth_level(&self, route: &str) -> AuthLevel {
+        self.route_auth[route]
+    }
+
+    /// whether `level` may run `cmd` with `/exec`.
+    pub fn may_exec(&self, level: AuthLevel, cmd: &str) -> bool {
+        if level >= self.auth_level("exec") {
+            return true;
+        }
+
+        let cmd = cmd.trim_start_matches('/');
+        self.exec
+            .allowed
+            .range(..=level)
+            .flat_map(|(_, allowed)| allowed)
+            .map(|prefix| prefix.trim_start_matches('/'))
+            .any(|prefix| {
+                cmd.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+            })
+    }
+}
+
+/// the routes whose auth level can be set in `[auth]`, and their defaults.
+pub const ROUTE_AUTH: &[(&str, AuthLevel)] = &[
+    ("stats", AuthLevel::Unauthed),
+    ("console", AuthLevel::Unauthed),
+    ("state", AuthLevel::Unauthed),
+    ("running", AuthLevel::Unauthed),
+    ("ping", AuthLevel::Unauthed),
+    ("list", AuthLevel::Unauthed),
+    ("progress", AuthLevel::Unauthed),
+    ("last_exit", AuthLevel::Unauthed),
+    ("capabilities", AuthLevel::Unauthed),
+    ("info", AuthLevel::Unauthed),
+    ("start", AuthLevel::Basic),
+    ("ip", AuthLevel::Basic),
+    ("stop", AuthLevel::Stop),
+    ("restart", AuthLevel::Stop),
+    ("wake", AuthLevel::Stop),
+    ("crashes", AuthLevel::Stop),
+    // running any command, see `[exec]` for the commands lower levels may run.
+    ("exec", AuthLevel::Stop),
+    ("reload", AuthLevel::Stop),
+];
+
+/// one runner the helper fronts.
+#[derive(Debug)]
+pub struct RunnerConfig {
+    /// shown to users, the id if not set.
+    pub name: String,
+    pub connection: Connection,
+    /// the mac address of the runner's machine, to wake it.
+    ///
+    /// always set for direct runners.
+    pub phys_addr: Option<[u8; 6]>,
+}
+
+/// how the helper reaches a runner.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Connection {
+    /// the helper connects to the runner at `addr`, presenting its `token` if it has one.
+    Direct { addr: Url, token: Option<String> },
+    /// the runner links to the helper, presenting this token.
+    Reverse { token: String },
+}
+
+impl RunnerConfig {
+    /// the runner's address, if the helper connects to it.
+    pub fn addr(&self) -> Option<&Url> {
+        match &self.connection {
+            Connection::Direct { addr, .. } => Some(addr),
+            Connection::Reverse { .. } => None,
+        }
+    }
+}
+
+/// the id of the only runner, when the config has no `[runners]`.
+pub const DEFAULT_RUNNER: &str = "default";
+
+/// the config file, before environment variables and defaults.
+///
+/// `runner_addr`, `runner_port`, `runner_token`, `link_token` and `phys_addr` are the only runner if there are no
+/// `runners`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    runner_addr: Option<String>,
+    runner_port: Option<u16>,
+    runner_token: Option<String>,
+    link_token: Option<String>,
+    phys_addr: Option<String>,
+    runners: BTreeMap<String, FileRunnerConfig>,
+    default_runner: Option<String>,
+    basic_token: Option<String>,
+    stop_token: Option<String>,
+    port: Option<u16>,
+    auth: BTreeMap<String, AuthLevel>,
+    exec: FileExecConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileExecConfig {
+    unauthed: Vec<String>,
+    basic: Vec<String>,
+    log: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileRunnerConfig {
+    name: Option<String>,
+    addr: Option<String>,
+    port: Option<u16>,
+    /// the runner's `token`.
+    token: Option<String>,
+    link_token: Option<String>,
+    phys_addr: Option<String>,
+}
+
+/// parses `00-1A-2B-3C-4D-5E`.
+fn parse_mac(mac: &str) -> Result<[u8; 6], String> {
+    let bytes: Vec<_> = mac.split('-').collect();
+    if bytes.len() != 6 {
+        return Err(format!("expected 6 bytes but got {}", bytes.len()));
+    }
+
+    let mut parsed = [0; 6];
+    for (i, hex) in bytes.iter().enumerate() {
+        parsed[i] = u8::from_str_radix(hex, 16)
+            .map_err(|_| format!("could not parse {hex:?} to a byte"))?;
+    }
+
+    Ok(parsed)
+}
+
+impl common::config::FileConfig for FileConfig {
+    type Config = Config;
+
+    fn apply_env(&mut self, problems: &mut Vec<String>) {
+        env_var("RUNNER_ADDR", &mut self.runner_addr, problems);
+        env_var("RUNNER_PORT", &mut self.runner_port, problems);
+        env_var("RUNNER_TOKEN", &mut self.runner_token, problems);
+        env_var("LINK_TOKEN", &mut self.link_token, problems);
+        env_var("PHYS_ADDR", &mut self.phys_addr, problems);
+        env_var("BASIC_TOKEN", &mut self.basic_token, problems);
+        env_var("STOP_TOKEN", &mut self.stop_token, problems);
+        env_var("HELPER_PORT", &mut self.port, problems);
+    }
+
+    fn validate(self, problems: &mut Vec<String>) -> Config {
+        let mut required = |field: Option<String>, name: &str, var: &str| {
+            field.unwrap_or_else(|| {
+                problems.push(format!("`{name}` (`{var}`) is required"));
+                String::new()
+            })
+        };
+
+        let basic_token = required(self.basic_token, "basic_token", "BASIC_TOKEN");
+        let stop_token = required(self.stop_token, "stop_token", "STOP_TOKEN");
+
+        if !basic_token.is_empty() && basic_token == stop_token {
+            problems.push("`basic_token` and `stop_token` must be different".to_string());
+        }
+
+        let flat = FileRunnerConfig {
+            name: None,
+            addr: self.runner_addr,
+            port: self.runner_port,
+            token: self.runner_token,
+            link_token: self.link_token,
+            phys_addr: self.phys_addr,
+        };
+
+        let runners: BTreeMap<_, _> = if self.runners.is_empty() {
+            let runner = flat.validate(DEFAULT_RUNNER, "", problems);
+            BTreeMap::from([(DEFAULT_RUNNER.to_string(), Arc::new(runner))])
+        } else {
+            if flat.addr.is_some()
+                || flat.port.is_some()
+                || flat.token.is_some()
+                || flat.link_token.is_some()
+                || flat.phys_addr.is_some()
+            {
+                problems.push(
+                    "`runner_addr`, `runner_port`, `runner_token`, `link_token` and `phys_addr` belong in `[runners.<id>]` when there are runners"
+                        .to_string(),
+                );
+            }
+
+            self.runners
+                .into_iter()
+                .map(|(id, runner)| {
+                    if !is_id(&id) {
+                        problems.push(format!(
+                            "runner id {id:?} may only have letters, digits, `-` and `_`"
+                        ));
+                    }
+                    if RESERVED_IDS.contains(&id.as_str()) {
+                        problems.push(format!(
+                            "runner id {id:?} is taken by the helper's own `/api/{id}` route"
+                        ));
+                    }
+                    let runner = runner.validate(&id, &format!("runners.{id}."), problems);
+                    (id, Arc::new(runner))
+                })
+                .collect()
+        };
+
+        let default_runner = match self.default_runner {
+            Some(id) if !runners.contains_key(&id) => {
+                problems.push(format!("`default_runner` ({id:?}) is not a runner"));
+                None
+            }
+            Some(id) => Some(id),
+            None if runners.len() == 1 => runners.keys().next().cloned(),
+            None => None,
+        };
+
+        let mut route_auth: BTreeMap<_, _> = ROUTE_AUTH.iter().copied().collect();
+        for (route, level) in self.auth {
+            match route_auth.get_mut(route.as_str()) {
+                Some(default) => *default = level,
+                None => problems.push(format!("`auth.{route}` is not a route")),
+            }
+        }
+
+        Config {
+            runners,
+            default_runner,
+            basic_token,
+            stop_token,
+            port: self.port.unwrap_or(1234),
+            route_auth,
+            exec: ExecConfig {
+                allowed: BTreeMap::from([
+                    (AuthLevel::Unauthed, self.exec.unauthed),
+                    (AuthLevel::Basic, self.exec.basic),
+                ]),
+                log: self.exec.log.unwrap_or_else(|| PathBuf::from("exec.log")),
+            },
+        }
+    }
+
+    fn unreloadable(old: &Config, new: &Config) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |changed: bool, name: &str| {
+            if changed {
+                problems.push(format!("`{name}` can only be changed by restarting"));
+            }
+        };
+
+        check(!new.runners.keys().eq(old.runners.keys()), "[runners]");
+        check(new.default_runner != old.default_runner, "default_runner");
+        check(new.port != old.port, "port");
+        for (id, runner) in &new.runners {
+            if let Some(old) = old.runners.get(id)
+                && runner.addr().is_some() != old.addr().is_some()
+            {
+                problems.push(format!(
+                    "whether runner {id} links to the helper can only be changed by restarting"
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+/// the helper's own routes in `api::helper_routes`, which runners are nested beside.
+const RESERVED_IDS: &[&str] = &["runners", "ticket", "reload", "link"];
+
+/// whether `id` can be used in a route.
+fn is_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl FileRunnerConfig {
+    /// `prefix` is where it is in the config, eg. `runners.pc.`, empty for the top-level keys.
+    fn validate(self, id: &str, prefix: &str, problems: &mut Vec<String>) -> RunnerConfig {
+        // the top-level keys are named differently, and can be set by environment variables.
+        let key = |key: &str, var: &str| {
+            if prefix.is_empty() {
+                let key = match key {
+                    "addr" | "port" | "token" => &format!("runner_{key}"),
+                    key => key,
+                };
+                format!("`{key}` (`{var}`)")
+            } else {
+                format!("`{prefix}{key}`")
+            }
+        };
+
+        let connection = match (self.addr, self.link_token) {
+            (Some(addr), None) => {
+                let port = self.port.unwrap_or(4321);
+                let addr = Url::from_str(&format!("http://{addr}:{port}")).unwrap_or_else(|err| {
+                    problems.push(format!("{} ({addr:?}): {err}", key("addr", "RUNNER_ADDR")));
+                    Url::from_str("http://localhost").expect("valid url")
+                });
+                if self.token.as_ref().is_some_and(String::is_empty) {
+                    problems.push(format!("{} is empty", key("token", "RUNNER_TOKEN")));
+                }
+                Connection::Direct {
+                    addr,
+                    token: self.token,
+                }
+            }
+            (None, Some(token)) => {
+                for (set, name, var) in [
+                    (self.port.is_some(), "port", "RUNNER_PORT"),
+                    (self.token.is_some(), "token", "RUNNER_TOKEN"),
+                ] {
+                    if set {
+                        problems.push(format!(
+                            "{} is only used with {}",
+                            key(name, var),
+                            key("addr", "RUNNER_ADDR")
+                        ));
+                    }
+                }
+                if token.is_empty() {
+                    problems.push(format!("{} is empty", key("link_token", "LINK_TOKEN")));
+                }
+                Connection::Reverse { token }
+            }
+            (addr, _) => {
+                let problem = if addr.is_some() {
+                    "may not both be set"
+                } else {
+                    "is required"
+                };
+                problems.push(format!(
+                    "{} or {} {problem}",
+                    key("addr", "RUNNER_ADDR"),
+                    key("link_token", "LINK_TOKEN")
+                ));
+                Connection::Direct {
+                    addr: Url::from_str("http://localhost").expect("valid url"),
+                    token: None,
+                }
+            }
+        };
+
+        // a direct runner is on the local network, so it can always be woken.
+        if self.phys_addr.is_none() && matches!(connection, Connection::Direct { .. }) {
+            problems.push(format!("{} is required", key("phys_addr", "PHYS_ADDR")));
+        }
+        let phys_addr = self.phys_addr.and_then(|phys_addr| {
+            parse_mac(&phys_addr)
+                .inspect_err(|err| {
+                    problems.push(format!(
+                        "{} ({phys_addr:?}): {err}",
+                        key("phys_addr", "PHYS_ADDR")
+                    ));
+                })
+                .ok()
+        });
+
+        RunnerConfig {
+            name: self.name.unwrap_or_else(|| id.to_string()),
+            connection,
+            phys_addr,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::config::FileConfig as _;
+
+    use super::{AuthLevel, Connection, DEFAULT_RUNNER, FileConfig, Url, parse_mac};
+
+    #[test]
+    fn validate() {
+        assert_eq!(
+            parse_mac("00-1A-2B-3C-4D-5E"),
+            Ok([0x00, 0x1A, 0x2B, 0x3C, 0x4D, 0x5E])
+        );
+        assert!(parse_mac("00-1A-2B").is_err());
+
+        let file: FileConfig = toml::from_str(
+            r#"
+            runner_addr = "192.168.1.2"
+            phys_addr = "00-1A-2B-3C-4D-ZZ"
+            basic_token = "same"
+            "#,
+        )
+        .unwrap();
+
+        let mut problems = Vec::new();
+        let config = file.validate(&mut problems);
+        // bad mac, missing `stop_token`.
+        assert_eq!(problems.len(), 2, "{problems:#?}");
+        assert_eq!(
+            config.runners[DEFAULT_RUNNER].addr().map(Url::as_str),
+            Some("http://192.168.1.2:4321/")
+        );
+
+        assert!(toml::from_str::<FileConfig>("runer_addr = 'typo'").is_err());
+    }
+
+    #[test]
+    fn runners() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            basic_token = "basic"
+            stop_token = "stop"
+            default_runner = "pc"
+
+            [runners.pc]
+            name = "Gaming PC"
+            addr = "192.168.1.2"
+            token = "runner-secret"
+            phys_addr = "00-1A-2B-3C-4D-5E"
+
+            [runners.laptop]
+            addr = "192.168.1.3"
+            port = 4322
+            phys_addr = "00-1A-2B-3C-4D-5F"
+            "#,
+        )
+        .unwrap();
+
+        let mut problems = Vec::new();
+        let config = file.validate(&mut problems);
+        assert!(problems.is_empty(), "{problems:#?}");
+        assert_eq!(config.default_runner.as_deref(), Some("pc"));
+        assert_eq!(config.runners["pc"].name, "Gaming PC");
+        assert_eq!(config.runners["laptop"].name, "laptop");
+        assert!(matches!(
+            &config.runners["pc"].connection,
+            Connection::Direct { token: Some(token), .. } if token == "runner-secret"
+        ));
+        assert_eq!(
+            config.runners["laptop"].addr().map(Url::as_str),
+            Some("http://192.168.1.3:4322/")
+        );
+
+        // a flat `runner_addr` with runners, and a runner without `phys_addr`.
+        let file: FileConfig = toml::from_str(
+            "basic_token = 'a'\nstop_token = 'b'\nrunner_addr = 'x'\n[runners.pc]\naddr = 'y'",
+        )
+        .unwrap();
+        let mut problems = Vec::new();
+        file.validate(&mut problems);
+        assert_eq!(problems.len(), 2, "{problems:#?}");
+
+        // an id taken by the helper's routes.
+        let file: FileConfig = toml::from_str(
+            "basic_token = 'a'\nstop_token = 'b'\n[runners.reload]\nlink_token = 'c'",
+        )
+        .unwrap();
+        let mut problems = Vec::new();
+        file.validate(&mut problems);
+        assert_eq!(problems.len(), 1, "{problems:#?}");
+    }
+
+    #[test]
+    fn link() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            basic_token = "basic"
+            stop_token = "stop"
+
+            [runners.pc]
+            link_token = "secret"
+
+            [runners.laptop]
+            addr = "192.168.1.3"
+            link_token = "secret"
+            phys_addr = "00-1A-2B-3C-4D-5F"
+            "#,
+        )
+        .unwrap();
+
+        let mut problems = Vec::new();
+        let config = file.validate(&mut problems);
+        // `laptop` has both `addr` and `link_token`.
+        assert_eq!(problems.len(), 1, "{problems:#?}");
+        assert_eq!(
+            config.runners["pc"].connection,
+            Connection::Reverse {
+                token: "secret".to_string()
+            }
+        );
+        assert_eq!(config.runners["pc"].phys_addr, None);
+    }
+
+    #[test]
+    fn auth() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            runner_addr = "192.168.1.2"
+            phys_addr = "00-1A-2B-3C-4D-5E"
+            basic_token = "basic"
+            stop_token = "stop"
+
+            [auth]
+            console = "basic"
+            stat = "stop"
+            "#,
+        )
+        .unwrap();
+
+        let mut problems = Vec::new();
+        let config = file.validate(&mut problems);
+        // `stat` is not a route.
+        assert_eq!(problems.len(), 1, "{problems:#?}");
+        assert_eq!(config.auth_level("console"), AuthLevel::Basic);
+        assert_eq!(config.auth_level("stats"), AuthLevel::Unauthed);
+        assert_eq!(config.auth_level("stop"), AuthLevel::Stop);
+
+        assert!(toml::from_str::<FileConfig>("[auth]\nconsole = 'admin'").is_err());
+    }
+
+    #[test]
+    fn exec() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            runner_addr = "192.168.1.2"
+            phys_addr = "00-1A-2B-3C-4D-5E"
+            basic_token = "basic"
+            stop_token = "stop"
+
+            [exec]
+            unauthed = ["list"]
+            basic = ["/say", "tps"]
+            "#,
+        )
+        .unwrap();
+        let mut problems = Vec::new();
+        let config = file.validate(&mut problems);
+        assert!(problems.is_empty(), "{problems:#?}");
+
+        assert!(config.may_exec(AuthLevel::Unauthed, "list"));
+        assert!(!config.may_exec(AuthLevel::Unauthed, "say hi"));
+        assert!(config.may_exec(AuthLevel::Basic, "/list"));
+        assert!(config.may_exec(AuthLevel::Basic, "say hi"));
+        assert!(!config.may_exec(AuthLevel::Basic, "tpsfake"));
+        assert!(!config.may_exec(AuthLevel::Basic, "stop"));
+        assert!(config.may_exec(AuthLevel::Stop, "stop"));
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::http::{HeaderValue, StatusCode};
+use common::{
+    HelperMessage, LINK_IDLE_TIMEOUT, PING_INTERVAL, RunnerMessage, decode_frame, encode_frame,
+};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use tokio::sync::{Notify, mpsc, oneshot};
+use tracing::instrument;
+
+use crate::{AppState, Instances, api::Forwarded};
+
+/// how long a forwarded request waits for the runner's response, under the 5s timeout of the helper's routes so the
+/// request is always forgotten here first.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// the one connection to a runner, which requests, stats, console and state are multiplexed over.
+///
+/// either we dial the runner, or it dials us in reverse connection mode.
+pub struct Link {
+    tx: mpsc::Sender<HelperMessage>,
+    /// the requests waiting for a response, by id.
+    pending: Mutex<HashMap<u64, oneshot::Sender<Forwarded>>>,
+    next_id: AtomicU64,
+    /// notified when the runner links again, so this one is closed.
+    replaced: Notify,
+}
+
+impl std::fmt::Debug for Link {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Link").finish_non_exhaustive()
+    }
+}
+
+impl Link {
+    /// requests `path` from the runner, `None` if the link drops or [`REQUEST_TIMEOUT`] passes before it responds.
+    pub async fn get(&self, path: String) -> Option<Forwarded> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().expect("not poisoned").insert(id, tx);
+
+        if self
+            .tx
+            .send(HelperMessage::Request { id, path })
+            .await
+            .is_err()
+        {
+            self.pending.lock().expect("not poisoned").remove(&id);
+            return None;
+        }
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(forwarded) => forwarded.ok(),
+            Err(_) => {
+                tracing::warn!("request {id} timed out");
+                self.pending.lock().expect("not poisoned").remove(&id);
+                None
+            }
+        }
+    }
+
+    fn respond(&self, id: u64, status: u16, content_type: Option<String>, body: Vec<u8>) {
+        let Some(tx) = self.pending.lock().expect("not poisoned").remove(&id) else {
+            tracing::warn!("got a response to unknown request {id}");
+            return;
+        };
+
+        let forwarded = Forwarded {
+            status: StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY),
+            content_type: content_type.and_then(|v| HeaderValue::from_str(&v).ok()),
+            body: body.into(),
+        };
+        // the request timed out.
+        let _ = tx.send(forwarded);
+    }
+}
+
+/// serves the runner's link until it drops, the runner links again, or its address or link token changes.
+///
+/// `stream` has the frames the runner sends, and `None` when it closes the link. the runner is pinged every
+/// [`PING_INTERVAL`], and the link is closed if it sends nothing for [`LINK_IDLE_TIMEOUT`].
+#[instrument(skip_all, fields(runner = %state.id))]
+pub async fn serve<S, R>(state: Arc<AppState>, mut sink: S, stream: R)
+where
+    S: Sink<Vec<u8>> + Unpin,
+    S::Error: Display,
+    R: Stream<Item = Option<Vec<u8>>>,
+{
+    let (tx, mut rx) = mpsc::channel(64);
+    let link = Arc::new(Link {
+        tx,
+        pending: Mutex::new(HashMap::new()),
+        next_id: AtomicU64::new(0),
+        replaced: Notify::new(),
+    });
+
+    let old = state
+        .link
+        .lock()
+        .expect("not poisoned")
+        .replace(link.clone());
+    if let Some(old) = old {
+        tracing::info!("runner linked again, closing the old link");
+        old.replaced.notify_one();
+    }
+    tracing::info!("runner linked");
+
+    let mut runner_changed = state.runner_changed.subscribe();
+
+    let writer = async {
+        while let Some(message) = rx.recv().await {
+            let frame = match encode_frame(&message) {
+                Ok(frame) => frame,
+                Err(err) => {
+                    tracing::error!("failed to serialize {message:?}: {err}");
+                    continue;
+                }
+            };
+            if let Err(err) = sink.send(frame).await {
+                tracing::warn!("link closed: {err}");
+                break;
+            }
+        }
+    };
+
+    let reader = async {
+        let mut stream = std::pin::pin!(stream);
+        loop {
+            let Ok(frame) = tokio::time::timeout(LINK_IDLE_TIMEOUT, stream.next()).await else {
+                tracing::warn!(
+                    "heard nothing from the runner in {LINK_IDLE_TIMEOUT:?}, closing the link"
+                );
+                break;
+            };
+            let Some(Some(frame)) = frame else {
+                break;
+            };
+            match decode_frame::<RunnerMessage>(&frame) {
+                Ok(RunnerMessage::Response {
+                    id,
+                    status,
+                    content_type,
+                    body,
+                }) => link.respond(id, status, content_type, body),
+                Ok(RunnerMessage::Linked { default_instance }) => {
+                    state.instances.send_replace(Some(Instances {
+                        default: default_instance,
+                        states: HashMap::new(),
+                    }));
+                }
+                Ok(RunnerMessage::Stats { instance, stats }) => {
+                    // no one is watching the stats.
+                    let _ = state.stats.send((instance, stats));
+                }
+                Ok(RunnerMessage::Console { instance, line }) => {
+                    // no one is watching the console.
+                    let _ = state.console.send((instance, line));
+                }
+                Ok(RunnerMessage::State { instance, update }) => {
+                    state.instances.send_modify(|instances| {
+                        instances
+                            .get_or_insert_default()
+                            .states
+                            .insert(instance, update);
+                    });
+                }
+                Ok(RunnerMessage::Pong) => {}
+                Err(err) => {
+                    tracing::warn!("could not read the runner's message: {err}");
+                    break;
+                }
+            }
+        }
+    };
+
+    let pinger = async {
+        let mut interval = tokio::time::interval(PING_INTERVAL);
+        loop {
+            interval.tick().await;
+            if link.tx.send(HelperMessage::Ping).await.is_err() {
+                break;
+            }
+        }
+    };
+
+    tokio::select! {
+        () = writer => {}
+        () = reader => {}
+        () = pinger => {}
+        () = link.replaced.notified() => {}
+        _ = runner_changed.changed() => tracing::info!("runner changed, closing the link"),
+    }
+
+    let mut current = state.link.lock().expect("not poisoned");
+    if current
+        .as_ref()
+        .is_some_and(|current| Arc::ptr_eq(current, &link))
+    {
+        *current = None;
+        // unknown until the runner links again.
+        state.instances.send_replace(None);
+    }
+    tracing::info!("runner unlinked");
+}
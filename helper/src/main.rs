@@ -1,19 +1,26 @@
 mod api;
+mod config;
+mod link;
+mod runners;
 mod tasks;
 
 use std::{
+    collections::HashMap,
     env,
     net::{Ipv4Addr, SocketAddrV4},
+    path::PathBuf,
+    process,
     str::FromStr,
-    sync::{Arc, LazyLock},
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
-use anyhow::Context;
 use axum::{Router, http::StatusCode};
-use reqwest::Url;
-use reqwest_websocket::Bytes;
-use tokio::{net::TcpListener, sync::broadcast};
+use common::{ConsoleLine, StateUpdate, Stats};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, watch},
+};
 use tower_http::{
     services::ServeDir,
     timeout::TimeoutLayer,
@@ -24,7 +31,9 @@ use tracing_subscriber::{
     EnvFilter, Layer, filter::Targets, layer::SubscriberExt, util::SubscriberInitExt,
 };
 
-use crate::tasks::{console_helper, stats_helper};
+use crate::config::{Connection, RunnerConfig, config};
+use crate::link::Link;
+use crate::tasks::link_helper;
 
 #[cfg(not(windows))]
 #[global_allocator]
@@ -34,35 +43,102 @@ static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-fn get_runner_addr() -> anyhow::Result<Url> {
-    let addr = env::var("RUNNER_ADDR").context("runner addr not found")?;
-    let port = env::var("RUNNER_PORT").unwrap_or("4321".to_string());
-
-    Ok(Url::from_str(&format!("http://{addr}:{port}"))?)
-}
-
-pub static RUNNER_ADDR: LazyLock<Url> = LazyLock::new(|| get_runner_addr().unwrap());
-
+/// one runner the helper fronts, which its routes are given.
+#[derive(Debug)]
 struct AppState {
+    /// its key in `[runners]`.
+    id: String,
     client: reqwest::Client,
-    stats: broadcast::Sender<Bytes>,
-    console: broadcast::Sender<String>,
+    /// each instance's stats, with its id.
+    stats: broadcast::Sender<(String, Stats)>,
+    /// each instance's console lines, with its id, filtered by the runner for each auth level.
+    console: broadcast::Sender<(String, ConsoleLine)>,
+    /// what the runner told us about its instances, `None` while it is not linked.
+    instances: watch::Sender<Option<Instances>>,
+    /// notified when a reload changes the runner's address or link token, so it is relinked.
+    runner_changed: watch::Sender<()>,
+    /// the runner's link, while it is connected.
+    link: Mutex<Option<Arc<Link>>>,
 }
 
 impl AppState {
-    fn new(stats: broadcast::Sender<Bytes>, console: broadcast::Sender<String>) -> Self {
+    fn new(id: String) -> Self {
         Self {
+            id,
             client: reqwest::Client::new(),
-            stats,
-            console,
+            stats: broadcast::channel(64).0,
+            console: broadcast::channel(256).0,
+            instances: watch::Sender::new(None),
+            runner_changed: watch::Sender::new(()),
+            link: Mutex::new(None),
         }
     }
+
+    /// this runner's part of the current config.
+    fn config(&self) -> Arc<RunnerConfig> {
+        config().runners[&self.id].clone()
+    }
+
+    fn link(&self) -> Option<Arc<Link>> {
+        self.link.lock().expect("not poisoned").clone()
+    }
+
+    /// the instance `asked` for, or the runner's default instance if `None`.
+    fn instance(&self, asked: Option<&str>) -> Option<String> {
+        match asked {
+            Some(asked) => Some(asked.to_string()),
+            None => self.instances.borrow().as_ref()?.default.clone(),
+        }
+    }
+}
+
+/// a runner's instances, as it sent them over the link.
+#[derive(Debug, Default)]
+struct Instances {
+    /// the instance the stats, console and state websockets show without `?instance=`.
+    default: Option<String>,
+    /// each instance's last state, by id.
+    states: HashMap<String, StateUpdate>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    if let Err(err) = dotenvy::dotenv() {
-        tracing::warn!("failed to read .env: {err}");
+    let mut config_path = None;
+    let mut check_config = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => config_path = Some(args.next().expect("`--config` needs a path")),
+            "--check-config" => check_config = true,
+            arg => {
+                eprintln!("unknown argument `{arg}`");
+                process::exit(2);
+            }
+        }
+    }
+
+    common::config::load_dotenv();
+
+    // a config file that was asked for must exist.
+    let config_path = config_path.or_else(|| env::var("HELPER_CONFIG").ok());
+    let required = config_path.is_some();
+    let config_path =
+        PathBuf::from(config_path.unwrap_or_else(|| config::DEFAULT_PATH.to_string()));
+
+    match config::load(&config_path, required) {
+        Ok(_) if check_config => {
+            println!("{} is valid", config_path.display());
+            return Ok(());
+        }
+        Ok(config) => config::init(config_path, required, config),
+        Err(problems) => {
+            eprintln!("invalid config ({}):", config_path.display());
+            for problem in problems {
+                eprintln!("- {problem}");
+            }
+            process::exit(1);
+        }
     }
 
     let filter = env::var(EnvFilter::DEFAULT_ENV).map_or(LevelFilter::INFO, |e| {
@@ -97,21 +173,30 @@ async fn main() -> anyhow::Result<()> {
         )
         .init();
 
-    LazyLock::force(&RUNNER_ADDR);
+    runners::init();
 
-    let (stats_tx, _rx) = broadcast::channel::<Bytes>(16);
-    let (console_tx, _rx) = broadcast::channel::<String>(16);
-    let app_state = Arc::new(AppState::new(stats_tx, console_tx));
+    let mut api = api::helper_routes();
+    for runner in runners::all() {
+        // a runner in reverse connection mode dials us instead.
+        if runner.config().addr().is_some() {
+            tokio::spawn(link_helper(runner.clone()));
+        }
 
-    tokio::spawn(stats_helper(app_state.clone()));
-    tokio::spawn(console_helper(app_state.clone()));
+        api = api.nest(
+            &format!("/{}", runner.id),
+            api::runner_routes().with_state(runner.clone()),
+        );
+    }
+    // so the web app keeps working with one runner.
+    if let Some(runner) = runners::default() {
+        api = api.merge(api::runner_routes().with_state(runner.clone()));
+    }
+    #[cfg(unix)]
+    tokio::spawn(tasks::reload_on_sighup());
 
     let app = Router::new()
         .fallback_service(ServeDir::new("static").precompressed_br())
-        .nest("/api", api::unauthed())
-        .nest("/api", api::basic_auth())
-        .nest("/api", api::stop_auth())
-        .with_state(app_state.clone())
+        .nest("/api", api)
         .layer(
             TraceLayer::new_for_http()
                 .on_request(DefaultOnRequest::new().level(Level::TRACE))
@@ -123,12 +208,20 @@ async fn main() -> anyhow::Result<()> {
             Duration::from_secs(5),
         ));
 
-    let port =
-        env::var("HELPER_PORT").map_or(1234, |p| p.parse().expect("configured port is not an int"));
+    let port = config().port;
     let ip = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port);
 
     tracing::info!("running server on :{port}");
-    tracing::info!("runner address set at {}", *RUNNER_ADDR);
+    for (id, runner) in &config().runners {
+        match &runner.connection {
+            Connection::Direct { addr, .. } => {
+                tracing::info!("runner {id} ({}) set at {addr}", runner.name);
+            }
+            Connection::Reverse { .. } => {
+                tracing::info!("runner {id} ({}) links to /api/link/{id}", runner.name);
+            }
+        }
+    }
 
     let listener = TcpListener::bind(ip).await?;
     axum::serve(listener, app)
@@ -0,0 +1,62 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, OnceLock},
+};
+
+use crate::{AppState, config::config};
+
+/// every runner in the config, keyed by id.
+///
+/// runners can only be added or removed by restarting, so this is set once.
+static RUNNERS: OnceLock<BTreeMap<String, Arc<AppState>>> = OnceLock::new();
+
+/// creates an [`AppState`] for every runner in the config.
+pub fn init() {
+    let runners = config()
+        .runners
+        .keys()
+        .map(|id| (id.clone(), Arc::new(AppState::new(id.clone()))))
+        .collect();
+
+    RUNNERS.set(runners).expect("runners are only created once");
+}
+
+pub fn all() -> impl Iterator<Item = &'static Arc<AppState>> {
+    RUNNERS
+        .get()
+        .expect("runners are created at startup")
+        .values()
+}
+
+/// the runner served at the routes without `/api/{runner}`, if there is one.
+pub fn default() -> Option<&'static Arc<AppState>> {
+    let id = config().default_runner.clone()?;
+    RUNNERS
+        .get()
+        .expect("runners are created at startup")
+        .get(&id)
+}
+
+pub fn get(id: &str) -> Option<&'static Arc<AppState>> {
+    RUNNERS
+        .get()
+        .expect("runners are created at startup")
+        .get(id)
+}
+
+/// reload the config, relinking runners whose address or link token changed.
+///
+/// the old config is kept if the new one has any problems.
+pub fn reload() -> Result<(), Vec<String>> {
+    let (old, new) = crate::config::reload()?;
+
+    for runner in all() {
+        if old.runners[&runner.id].connection != new.runners[&runner.id].connection {
+            tracing::info!("runner {} connection changed", runner.id);
+            runner.runner_changed.send_replace(());
+        }
+    }
+
+    tracing::info!("reloaded config");
+    Ok(())
+}
@@ -1,104 +1,94 @@
-use std::{sync::Arc, time::Duration};
+use std::{future, sync::Arc, time::Duration};
 
-use futures_util::StreamExt;
+use futures_util::{SinkExt, StreamExt};
 use helper::UrlExt;
 use reqwest_websocket::{self as reqwest_ws, Message, Upgrade};
 use tokio::signal;
 use tracing::instrument;
 
-use crate::{AppState, RUNNER_ADDR};
-
-pub async fn websocket(
-    client: &reqwest::Client,
-    url: impl reqwest::IntoUrl,
-) -> Result<reqwest_ws::WebSocket, reqwest_ws::Error> {
-    client
-        .get(url)
-        .timeout(Duration::from_secs(4))
-        .upgrade()
-        .send()
-        .await?
-        .into_websocket()
-        .await
-}
+use crate::{AppState, config::Connection, link, runners};
 
-const WS_TIMEOUT: Duration = Duration::from_secs(2);
+/// how long to wait before reconnecting the first time, doubled for each failure in a row.
+const BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// keeps a link open to a runner we dial, reconnecting if it drops or the runner's address changes.
+#[instrument(skip_all, fields(runner = %state.id))]
+pub async fn link_helper(state: Arc<AppState>) {
+    let mut runner_changed = state.runner_changed.subscribe();
+    let mut backoff = BACKOFF;
 
-/// transmits the stats from the runner to a channel.
-#[instrument(skip_all)]
-pub async fn stats_helper(state: Arc<AppState>) {
     loop {
-        let runner_ws = websocket(&state.client, RUNNER_ADDR.join_unchecked("stats")).await;
-        let mut runner_ws = match runner_ws {
-            Ok(ws) => ws,
-            Err(err) => {
-                tracing::error!("failed to connect ({err}), waiting {WS_TIMEOUT:?}..");
-                tokio::time::sleep(WS_TIMEOUT).await;
-                continue;
-            }
+        runner_changed.mark_unchanged();
+        let runner = state.config();
+        let Connection::Direct { addr, token } = &runner.connection else {
+            tracing::warn!("runner links to us, not dialing it");
+            return;
         };
 
-        tracing::info!("connected to stats");
-
-        while let Some(message) = runner_ws.next().await {
-            let message = match message {
-                Ok(message) => message,
-                Err(err) => {
-                    tracing::warn!("stats ws closed: {err}");
-                    break;
-                }
-            };
-
-            if let Message::Binary(bytes) = message {
-                if let Err(err) = state.stats.send(bytes) {
-                    tracing::warn!("failed to broadcast: {err}");
+        let ws = async {
+            let mut req = state
+                .client
+                .get(addr.join_unchecked("link"))
+                .timeout(Duration::from_secs(4));
+            if let Some(token) = token {
+                req = req.bearer_auth(token);
+            }
+            req.upgrade().send().await?.into_websocket().await
+        };
+        match ws.await {
+            Ok(ws) => {
+                backoff = BACKOFF;
+
+                let (sink, stream) = ws.split();
+                let sink = sink.with(|frame: Vec<u8>| {
+                    future::ready(Ok::<_, reqwest_ws::Error>(Message::Binary(frame.into())))
+                });
+                let stream = stream.filter_map(|message| {
+                    future::ready(match message {
+                        Ok(Message::Binary(frame)) => Some(Some(frame.to_vec())),
+                        Ok(Message::Close { .. }) => Some(None),
+                        Ok(_) => None,
+                        Err(err) => {
+                            tracing::warn!("link closed: {err}");
+                            Some(None)
+                        }
+                    })
+                });
+                link::serve(state.clone(), sink, stream).await;
+
+                // reconnect right away to the new address.
+                if runner_changed.has_changed().unwrap_or(false) {
+                    continue;
                 }
-            } else {
-                tracing::warn!("expected binary, got something else");
             }
+            Err(err) => tracing::error!("failed to link ({err})"),
         }
 
-        tracing::warn!("waiting {WS_TIMEOUT:?} to reconnect..");
-        tokio::time::sleep(WS_TIMEOUT).await;
+        tracing::warn!("waiting {backoff:?} to reconnect..");
+        tokio::select! {
+            () = tokio::time::sleep(backoff) => {}
+            _ = runner_changed.changed() => {}
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
     }
 }
 
-/// transmits the stats from the runner to a channel.
+/// reloads the config whenever we get `SIGHUP`.
+#[cfg(unix)]
 #[instrument(skip_all)]
-pub async fn console_helper(state: Arc<AppState>) {
-    loop {
-        let runner_ws = websocket(&state.client, RUNNER_ADDR.join_unchecked("console")).await;
-        let mut runner_ws = match runner_ws {
-            Ok(ws) => ws,
-            Err(err) => {
-                tracing::error!("failed to connect ({err}), waiting {WS_TIMEOUT:?}..");
-                tokio::time::sleep(WS_TIMEOUT).await;
-                continue;
-            }
-        };
-
-        tracing::info!("connected to console");
-
-        while let Some(message) = runner_ws.next().await {
-            let message = match message {
-                Ok(message) => message,
-                Err(err) => {
-                    tracing::warn!("console ws closed: {err}");
-                    break;
-                }
-            };
-
-            if let Message::Text(text) = message {
-                if let Err(err) = state.console.send(text) {
-                    tracing::warn!("failed to broadcast: {err}");
-                }
-            } else {
-                tracing::warn!("expected text, got something else");
+pub async fn reload_on_sighup() {
+    let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())
+        .expect("failed to install signal handler");
+
+    while hangup.recv().await.is_some() {
+        tracing::info!("got SIGHUP, reloading config");
+        if let Err(problems) = runners::reload() {
+            tracing::warn!("not reloading config:");
+            for problem in problems {
+                tracing::warn!("- {problem}");
             }
         }
-
-        tracing::warn!("waiting {WS_TIMEOUT:?} to reconnect..");
-        tokio::time::sleep(WS_TIMEOUT).await;
     }
 }
 
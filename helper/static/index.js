@@ -8,6 +8,10 @@ const serverUptime = document.getElementById("serverUptime");
 const serverInfo = document.getElementById("serverInfo");
 const serverMods = document.getElementById("serverMods");
 
+// localStorage keys of the passwords, declared here since the websockets use them as soon as the page loads
+const AUTH_PASSWORD_KEY = "auth_password";
+const STOP_PASSWORD_KEY = "stop_password";
+
 // Utility functions
 function showStatus(message, isError = false) {
   statusElem.innerHTML = `<div class="status ${
@@ -225,7 +229,7 @@ async function startServer() {
       const text = await response.text();
 
       if (response.ok) {
-        showStatus(`Server started: ${text}`);
+        showStatus(`Server starting: ${text}`);
         consoleElement.innerText = "";
         serverInfoRetryArmed = true;
         serverInfoRetryCount = 0;
@@ -344,6 +348,43 @@ async function getServerIp() {
   }, AUTH_PASSWORD_KEY);
 }
 
+async function runCommand() {
+  const command = consoleInput.value.trim();
+  if (!command) return;
+
+  // stop users may run any command, basic users only the allowed ones
+  const passwordKey = getStoredPassword(STOP_PASSWORD_KEY)
+    ? STOP_PASSWORD_KEY
+    : AUTH_PASSWORD_KEY;
+
+  await executeWithAuth(async () => {
+    try {
+      consoleInput.disabled = true;
+      const response = await makeAuthenticatedRequest(
+        `/api/exec/${encodeURIComponent(command)}`,
+        passwordKey
+      );
+      const text = await response.text();
+
+      if (response.ok) {
+        consoleInput.value = "";
+      } else if (response.status == 403) {
+        showStatus(`Not allowed to run \`${escapeHtml(command)}\``, true);
+      } else {
+        showStatus(`Failed to run command: ${text}`, true);
+      }
+    } catch (error) {
+      if (error.message === "INVALID_PASSWORD") {
+        throw error;
+      }
+      showStatus(`Error running command: ${error.message}`, true);
+    } finally {
+      consoleInput.disabled = false;
+      consoleInput.focus();
+    }
+  }, passwordKey);
+}
+
 var port = null;
 
 async function loadServerInfo() {
@@ -433,12 +474,36 @@ setInterval(() => {
  */
 let statsSocket = null;
 
+/**
+ * The url of a websocket, with a ticket for the most trusted stored password, since websockets can't send the token header.
+ * @param {string} path
+ */
+async function websocketUrl(path) {
+  const password = getStoredPassword(STOP_PASSWORD_KEY) ?? getStoredPassword(AUTH_PASSWORD_KEY);
+  if (!password) {
+    return path;
+  }
+
+  try {
+    const response = await fetch("/api/ticket", {
+      signal: AbortSignal.timeout(2500),
+      headers: { token: password },
+    });
+    if (response.ok) {
+      return `${path}?ticket=${await response.text()}`;
+    }
+  } catch (error) {
+    console.error("Failed to get a websocket ticket:", error);
+  }
+  return path;
+}
+
 // WebSocket connection for stats
-function connectStats() {
+async function connectStats() {
   try {
     connectionStatus.innerText = "Connecting to stats..";
     connectionStatus.className = "connection-status connecting";
-    statsSocket = new WebSocket("/api/stats");
+    statsSocket = new WebSocket(await websocketUrl("/api/stats"));
 
     statsSocket.onopen = () => {
       lastStatsMsg = performance.now();
@@ -487,15 +552,50 @@ const serverCpu = document.getElementById("serverCpu");
 const serverDisk = document.getElementById("serverDisk");
 
 const serverRunningStatus = document.getElementById("serverRunning");
+const startupProgress = document.getElementById("startupProgress");
+const startupProgressFill = document.getElementById("startupProgressFill");
+const startupProgressStage = document.getElementById("startupProgressStage");
+
+function hideStartupProgress() {
+  startupProgress.style.display = "none";
+}
+
+async function loadStartupProgress() {
+  try {
+    const response = await fetch("/api/progress", {
+      signal: AbortSignal.timeout(2500),
+    });
+    if (!response.ok) {
+      hideStartupProgress();
+      return;
+    }
 
-function setServerRunning(running) {
-  if (running) {
+    const { percent, stage } = await response.json();
+    startupProgressFill.style.width = `${percent}%`;
+    startupProgressStage.textContent = `${stage} (${percent}%)`;
+    startupProgress.style.display = "block";
+  } catch {
+    hideStartupProgress();
+  }
+}
+
+const RUNNING_STATES = ["starting", "ready", "stopping"];
+
+function isRunningState(state) {
+  return RUNNING_STATES.includes(state);
+}
+
+/**
+ * @param {string} state the runner's lifecycle state
+ */
+function setServerRunning(state) {
+  if (isRunningState(state)) {
     serverRunningStatus.className = "running";
-    serverRunningStatus.innerText = "running";
+    serverRunningStatus.innerText = state === "ready" ? "running" : state;
     serverUptime.style.display = "inline";
   } else {
     serverRunningStatus.className = "stopped";
-    serverRunningStatus.innerText = "stopped";
+    serverRunningStatus.innerText = state;
     serverUptime.style.display = "none";
   }
 }
@@ -504,9 +604,14 @@ setInterval(() => {
   fetch("/api/running", { signal: AbortSignal.timeout(2500) })
     .then(async (resp) => {
       if (resp.ok) {
-        let text = await resp.text();
-        const running = text == "true";
-        setServerRunning(running);
+        const { state } = await resp.json();
+        const running = isRunningState(state);
+        setServerRunning(state);
+        if (state === "starting") {
+          loadStartupProgress();
+        } else {
+          hideStartupProgress();
+        }
         if (running !== lastServerRunning) {
           lastServerRunning = running;
           if (running) {
@@ -524,7 +629,8 @@ setInterval(() => {
           updateServerUptime();
         }
       } else {
-        setServerRunning(false);
+        setServerRunning("stopped");
+        hideStartupProgress();
         if (lastServerRunning !== false) {
           lastServerRunning = false;
           serverStartedAtMs = null;
@@ -538,7 +644,8 @@ setInterval(() => {
       }
     })
     .catch(() => {
-      setServerRunning(false);
+      setServerRunning("stopped");
+      hideStartupProgress();
       if (lastServerRunning !== false) {
         lastServerRunning = false;
         serverStartedAtMs = null;
@@ -631,12 +738,16 @@ const startBtn = document.getElementById("startBtn");
 const stopBtn = document.getElementById("stopBtn");
 const ipBtn = document.getElementById("ipBtn");
 const wakeBtn = document.getElementById("wakeBtn");
+const consoleInput = document.getElementById("consoleInput");
 
 // Event listeners
 startBtn.onclick = startServer;
 stopBtn.onclick = stopServer;
 ipBtn.onclick = getServerIp;
 wakeBtn.onclick = wakeServer;
+consoleInput.onkeydown = (e) => {
+  if (e.key === "Enter") runCommand();
+};
 
 serverIp.onclick = async (ev) => {
   const ip = ev.target.innerText.split(": ");
@@ -702,10 +813,10 @@ function updateConsoleStatus(message, status = "disconnected") {
   consoleStatusElement.className = `console-status ${status}`;
 }
 
-function connectConsole() {
+async function connectConsole() {
   try {
     updateConsoleStatus("Connecting to console...", "connecting");
-    consoleSocket = new WebSocket("/api/console");
+    consoleSocket = new WebSocket(await websocketUrl("/api/console"));
 
     consoleSocket.onopen = async () => {
       if (consoleFirstConnect) {
@@ -756,9 +867,6 @@ updateConsoleStatus("Console not connected", "disconnected");
 connectConsole();
 
 // Password management
-const AUTH_PASSWORD_KEY = "auth_password";
-const STOP_PASSWORD_KEY = "stop_password";
-
 function getStoredPassword(key) {
   return localStorage.getItem(key);
 }
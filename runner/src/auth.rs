@@ -0,0 +1,99 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use common::same_token;
+
+use crate::config::config;
+
+/// rejects requests from addresses not in `allowed_ips`, or without the `token` as a bearer token.
+pub async fn authorize(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let config = config();
+    match check(
+        &config.allowed_ips,
+        config.token.as_deref(),
+        addr.ip(),
+        req.headers(),
+    ) {
+        Ok(()) => next.run(req).await,
+        Err(rejection) => {
+            tracing::warn!("rejected {} from {addr}: {}", req.uri(), rejection.1);
+            rejection.into_response()
+        }
+    }
+}
+
+fn check(
+    allowed_ips: &[IpAddr],
+    token: Option<&str>,
+    ip: IpAddr,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, &'static str)> {
+    // an ipv4 client of an ipv6 socket connects from eg. `::ffff:192.168.1.2`.
+    let ip = ip.to_canonical();
+    if !allowed_ips.is_empty() && !allowed_ips.contains(&ip) {
+        return Err((StatusCode::FORBIDDEN, "address not allowed"));
+    }
+
+    let Some(token) = token else {
+        return Ok(());
+    };
+    let given = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.as_bytes().strip_prefix(b"Bearer "));
+    match given {
+        Some(given) if same_token(given, token.as_bytes()) => Ok(()),
+        _ => Err((StatusCode::UNAUTHORIZED, "wrong or missing token")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+
+    use super::check;
+
+    #[test]
+    fn token_and_ip() {
+        let helper: IpAddr = "192.168.1.2".parse().unwrap();
+        let allowed = [helper];
+        let token = Some("secret");
+
+        let mut headers = HeaderMap::new();
+        assert!(check(&[], None, helper, &headers).is_ok());
+        assert_eq!(
+            check(&allowed, token, helper, &headers).unwrap_err().0,
+            StatusCode::UNAUTHORIZED
+        );
+
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        assert!(check(&allowed, token, helper, &headers).is_ok());
+        // an ipv4-mapped ipv6 address is the same address.
+        let mapped = "::ffff:192.168.1.2".parse().unwrap();
+        assert!(check(&allowed, token, mapped, &headers).is_ok());
+        let other = "192.168.1.3".parse().unwrap();
+        assert_eq!(
+            check(&allowed, token, other, &headers).unwrap_err().0,
+            StatusCode::FORBIDDEN
+        );
+
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secreT"),
+        );
+        assert!(check(&allowed, token, helper, &headers).is_err());
+    }
+}
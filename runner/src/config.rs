@@ -0,0 +1,786 @@
+use std::{
+    collections::BTreeMap,
+    net::{IpAddr, Ipv4Addr},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use common::{
+    AuthLevel,
+    config::{Store, env_var, var},
+};
+use regex::Regex;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::{
+    filters::{Filter, RuleConfig},
+    games::{self, Game, GameArgs},
+};
+
+/// the config file used if `--config` and `RUNNER_CONFIG` are not set.
+pub const DEFAULT_PATH: &str = "runner.toml";
+
+static CONFIG: Store<FileConfig> = Store::new();
+
+/// the current config, which may be swapped by [`reload`].
+pub fn config() -> Arc<Config> {
+    CONFIG.get()
+}
+
+pub fn init(path: PathBuf, required: bool, config: Config) {
+    CONFIG.init(path, required, config);
+}
+
+/// see [`Store::reload`], returning the new config.
+pub fn reload() -> Result<Arc<Config>, Vec<String>> {
+    CONFIG.reload().map(|(_, new)| new)
+}
+
+/// see [`common::config::load`].
+pub fn load(path: &Path, required: bool) -> Result<Config, Vec<String>> {
+    common::config::load::<FileConfig>(path, required)
+}
+
+#[derive(Debug)]
+pub struct Config {
+    /// keyed by id, which is used in the routes.
+    pub instances: BTreeMap<String, Arc<InstanceConfig>>,
+    /// the instance served at the routes without `/instances/{id}`.
+    pub default_instance: Option<String>,
+    /// how many instances may run at once, unlimited if not set.
+    pub max_running: Option<usize>,
+    pub port: u16,
+    /// the address to listen on, every address if not set.
+    pub bind: IpAddr,
+    /// the only addresses that may connect, any if empty.
+    pub allowed_ips: Vec<IpAddr>,
+    /// the bearer token every request must have, so only the helper can control us.
+    pub token: Option<String>,
+    /// show the game servers' consoles in our stdout.
+    pub show_console: bool,
+    /// only required for terraria.
+    pub steam_apikey: Option<String>,
+    /// lets the console websocket be read unfiltered.
+    pub secret: Option<String>,
+    /// how each auth level sees each game's console, for every game and level.
+    pub filters: BTreeMap<(&'static str, AuthLevel), Filter>,
+    pub state_file: PathBuf,
+    pub detach_on_shutdown: bool,
+    /// in seconds, largest first.
+    pub countdown_intervals: Vec<u64>,
+    pub crash: CrashConfig,
+    /// set to keep a link open to the helper, instead of the helper connecting to us.
+    pub helper: Option<HelperConfig>,
+}
+
+/// the helper to dial out to, so it can reach a runner on another network.
+#[derive(Debug)]
+pub struct HelperConfig {
+    /// eg. `https://helper.example.com`.
+    pub url: Url,
+    /// this runner's id in the helper's `[runners]`.
+    pub runner: String,
+    /// the runner's `link_token` in the helper's config.
+    pub token: String,
+}
+
+/// one game server run by the runner.
+#[derive(Debug, Default)]
+pub struct InstanceConfig {
+    pub server_dir: PathBuf,
+    /// the [`games::Game::name`] of the game to run.
+    pub server_type: String,
+    /// empty if not set.
+    pub game_args: GameArgs,
+    /// where its crash reports are saved, `crash.dir` itself if the config has no `[instances]`, else a directory in it named after the instance.
+    pub crash_dir: PathBuf,
+    pub restart: RestartConfig,
+    pub stop: StopConfig,
+    /// only set if `server_type` is `custom`.
+    pub custom: Option<CustomConfig>,
+}
+
+/// `crash.dir` is each instance's [`InstanceConfig::crash_dir`].
+#[derive(Debug)]
+pub struct CrashConfig {
+    /// how many reports to keep before deleting the oldest.
+    pub keep: usize,
+    /// how many console lines to save with a report.
+    pub console_lines: usize,
+}
+
+/// a game without built-in support, declared in the config.
+#[derive(Debug)]
+pub struct CustomConfig {
+    /// shown as the server's version.
+    pub name: String,
+    /// relative to `server_dir` if it exists there, else found in `PATH`.
+    pub executable: PathBuf,
+    /// given before `game_args`.
+    pub args: GameArgs,
+    /// relative to `server_dir`.
+    pub working_dir: PathBuf,
+    pub stop: CustomStop,
+    /// eg. `/list`, `/list` is unsupported if not set.
+    pub list_command: Option<String>,
+    /// a console line showing the server is joinable, else it is when `port` accepts connections.
+    pub ready: Option<Regex>,
+    pub port: Option<u16>,
+}
+
+/// how to gracefully stop a custom game.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum CustomStop {
+    /// written to the console.
+    Command(String),
+    Signal(StopSignal),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StopSignal {
+    /// `SIGINT`, or `CTRL_BREAK_EVENT` on windows.
+    Interrupt,
+    /// `SIGTERM`, not supported on windows.
+    Terminate,
+}
+
+/// overrides the game's [`crate::restart::RestartPolicy`], durations are in seconds.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RestartConfig {
+    pub on_crash: Option<bool>,
+    pub backoff: Option<u64>,
+    pub max_backoff: Option<u64>,
+    pub max: Option<u32>,
+    pub window: Option<u64>,
+}
+
+/// overrides the game's [`crate::stopping::StopPolicy`], durations are in seconds.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StopConfig {
+    pub timeout: Option<u64>,
+    pub save_timeout: Option<u64>,
+}
+
+impl RestartConfig {
+    /// the fields that are not set are taken from `defaults`.
+    fn or(self, defaults: Self) -> Self {
+        Self {
+            on_crash: self.on_crash.or(defaults.on_crash),
+            backoff: self.backoff.or(defaults.backoff),
+            max_backoff: self.max_backoff.or(defaults.max_backoff),
+            max: self.max.or(defaults.max),
+            window: self.window.or(defaults.window),
+        }
+    }
+}
+
+impl StopConfig {
+    /// the fields that are not set are taken from `defaults`.
+    fn or(self, defaults: Self) -> Self {
+        Self {
+            timeout: self.timeout.or(defaults.timeout),
+            save_timeout: self.save_timeout.or(defaults.save_timeout),
+        }
+    }
+}
+
+impl Config {
+    /// how `level` sees `game`'s console.
+    pub fn filter(&self, game: &dyn Game, level: AuthLevel) -> &Filter {
+        &self.filters[&(game.name(), level)]
+    }
+}
+
+/// the config file, before environment variables and defaults.
+///
+/// `server_dir`, `server_type`, `game_args` and `custom` are the only instance if there are no `instances`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server_dir: Option<PathBuf>,
+    server_type: Option<String>,
+    instances: BTreeMap<String, FileInstanceConfig>,
+    default_instance: Option<String>,
+    max_running: Option<usize>,
+    port: Option<u16>,
+    bind: Option<IpAddr>,
+    allowed_ips: Option<Vec<IpAddr>>,
+    token: Option<String>,
+    show_console: Option<bool>,
+    game_args: Option<GameArgs>,
+    steam_apikey: Option<String>,
+    secret: Option<String>,
+    state_file: Option<PathBuf>,
+    detach_on_shutdown: Option<bool>,
+    countdown_intervals: Option<Vec<u64>>,
+    crash: FileCrashConfig,
+    restart: RestartConfig,
+    stop: StopConfig,
+    custom: Option<FileCustomConfig>,
+    helper: Option<FileHelperConfig>,
+    /// keyed by game.
+    filters: BTreeMap<String, FileFilters>,
+}
+
+/// the rules of each auth level, which gets the defaults if not set.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileFilters {
+    unauthed: Option<Vec<RuleConfig>>,
+    basic: Option<Vec<RuleConfig>>,
+    stop: Option<Vec<RuleConfig>>,
+}
+
+impl FileFilters {
+    /// the rules of `level`, and its key.
+    fn take(&mut self, level: AuthLevel) -> (&'static str, Option<Vec<RuleConfig>>) {
+        match level {
+            AuthLevel::Unauthed => ("unauthed", self.unauthed.take()),
+            AuthLevel::Basic => ("basic", self.basic.take()),
+            AuthLevel::Stop => ("stop", self.stop.take()),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileHelperConfig {
+    url: Option<String>,
+    runner: Option<String>,
+    token: Option<String>,
+}
+
+impl FileHelperConfig {
+    fn validate(self, problems: &mut Vec<String>) -> HelperConfig {
+        let mut required = |field: Option<String>, name: &str| {
+            field.unwrap_or_else(|| {
+                problems.push(format!("`helper.{name}` is required"));
+                String::new()
+            })
+        };
+        let url = required(self.url, "url");
+        let runner = required(self.runner, "runner");
+        let token = required(self.token, "token");
+
+        let url = Url::parse(&url).unwrap_or_else(|err| {
+            if !url.is_empty() {
+                problems.push(format!("`helper.url` ({url:?}): {err}"));
+            }
+            Url::parse("http://localhost").expect("valid url")
+        });
+
+        HelperConfig { url, runner, token }
+    }
+}
+
+/// the top-level `restart` and `stop` are the defaults of every instance.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileInstanceConfig {
+    server_dir: Option<PathBuf>,
+    server_type: Option<String>,
+    game_args: Option<GameArgs>,
+    restart: RestartConfig,
+    stop: StopConfig,
+    custom: Option<FileCustomConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileCrashConfig {
+    dir: Option<PathBuf>,
+    keep: Option<usize>,
+    console_lines: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileCustomConfig {
+    name: Option<String>,
+    executable: Option<PathBuf>,
+    args: GameArgs,
+    working_dir: Option<PathBuf>,
+    stop: Option<CustomStop>,
+    list_command: Option<String>,
+    ready: Option<String>,
+    port: Option<u16>,
+}
+
+impl FileCustomConfig {
+    /// `prefix` is where it is in the config, eg. `instances.vs.`.
+    fn validate(self, prefix: &str, problems: &mut Vec<String>) -> CustomConfig {
+        let executable = self.executable.unwrap_or_else(|| {
+            problems.push(format!("`{prefix}custom.executable` is required"));
+            PathBuf::new()
+        });
+
+        let ready = self.ready.and_then(|ready| {
+            Regex::new(&ready)
+                .inspect_err(|err| problems.push(format!("`{prefix}custom.ready`: {err}")))
+                .ok()
+        });
+        if ready.is_none() && self.port.is_none() {
+            problems.push(format!(
+                "`{prefix}custom.ready` or `{prefix}custom.port` is required to tell when it is ready"
+            ));
+        }
+
+        CustomConfig {
+            name: self.name.unwrap_or_else(|| "custom".to_string()),
+            executable,
+            args: self.args,
+            working_dir: self.working_dir.unwrap_or_default(),
+            stop: self
+                .stop
+                .unwrap_or(CustomStop::Signal(StopSignal::Interrupt)),
+            list_command: self.list_command,
+            ready,
+            port: self.port,
+        }
+    }
+}
+
+impl common::config::FileConfig for FileConfig {
+    type Config = Config;
+
+    fn apply_env(&mut self, problems: &mut Vec<String>) {
+        env_var("SERVER_DIR", &mut self.server_dir, problems);
+        env_var("SERVER_TYPE", &mut self.server_type, problems);
+        env_var("RUNNER_PORT", &mut self.port, problems);
+        env_var("BIND_ADDR", &mut self.bind, problems);
+        env_var("RUNNER_TOKEN", &mut self.token, problems);
+        env_var("SHOW_CONSOLE", &mut self.show_console, problems);
+        env_var("GAME_ARGS", &mut self.game_args, problems);
+        env_var("STEAM_APIKEY", &mut self.steam_apikey, problems);
+        env_var("SECRET", &mut self.secret, problems);
+        env_var("STATE_FILE", &mut self.state_file, problems);
+        env_var("DETACH_ON_SHUTDOWN", &mut self.detach_on_shutdown, problems);
+        env_var("MAX_RUNNING", &mut self.max_running, problems);
+
+        if let Some(ips) = var("ALLOWED_IPS") {
+            match ips.split(',').map(|ip| ip.trim().parse()).collect() {
+                Ok(ips) => self.allowed_ips = Some(ips),
+                Err(err) => problems.push(format!("`ALLOWED_IPS` ({ips:?}): {err}")),
+            }
+        }
+
+        if let Some(intervals) = var("COUNTDOWN_INTERVALS") {
+            match intervals.split(',').map(|i| i.trim().parse()).collect() {
+                Ok(intervals) => self.countdown_intervals = Some(intervals),
+                Err(err) => problems.push(format!("`COUNTDOWN_INTERVALS` ({intervals:?}): {err}")),
+            }
+        }
+
+        env_var("CRASH_DIR", &mut self.crash.dir, problems);
+        env_var("CRASH_REPORTS_KEEP", &mut self.crash.keep, problems);
+        env_var(
+            "CRASH_CONSOLE_LINES",
+            &mut self.crash.console_lines,
+            problems,
+        );
+
+        env_var("RESTART_ON_CRASH", &mut self.restart.on_crash, problems);
+        env_var("RESTART_BACKOFF", &mut self.restart.backoff, problems);
+        env_var(
+            "RESTART_MAX_BACKOFF",
+            &mut self.restart.max_backoff,
+            problems,
+        );
+        env_var("RESTART_MAX", &mut self.restart.max, problems);
+        env_var("RESTART_WINDOW", &mut self.restart.window, problems);
+
+        env_var("STOP_TIMEOUT", &mut self.stop.timeout, problems);
+        env_var("SAVE_TIMEOUT", &mut self.stop.save_timeout, problems);
+    }
+
+    fn validate(self, problems: &mut Vec<String>) -> Config {
+        let mut countdown_intervals = self
+            .countdown_intervals
+            .unwrap_or_else(|| vec![600, 300, 120, 60, 30, 10, 5, 4, 3, 2, 1]);
+        countdown_intervals.sort_unstable_by(|a, b| b.cmp(a));
+        countdown_intervals.dedup();
+
+        let console_lines = self.crash.console_lines.unwrap_or(200);
+        if console_lines == 0 {
+            problems.push("`crash.console_lines` must be at least 1".to_string());
+        }
+        let crash_dir = self.crash.dir.unwrap_or_else(|| PathBuf::from("crashes"));
+
+        let flat = FileInstanceConfig {
+            server_dir: self.server_dir,
+            server_type: self.server_type,
+            game_args: self.game_args,
+            custom: self.custom,
+            ..FileInstanceConfig::default()
+        };
+        let defaults = (self.restart, self.stop);
+
+        let instances: BTreeMap<_, _> = if self.instances.is_empty() {
+            let instance = flat.validate("", defaults, crash_dir.clone(), problems);
+            BTreeMap::from([(DEFAULT_INSTANCE.to_string(), Arc::new(instance))])
+        } else {
+            if flat.server_dir.is_some()
+                || flat.server_type.is_some()
+                || flat.game_args.is_some()
+                || flat.custom.is_some()
+            {
+                problems.push(
+                    "`server_dir`, `server_type`, `game_args` and `[custom]` belong in `[instances.<id>]` when there are instances"
+                        .to_string(),
+                );
+            }
+
+            self.instances
+                .into_iter()
+                .map(|(id, instance)| {
+                    if !is_id(&id) {
+                        problems.push(format!(
+                            "instance id {id:?} may only have letters, digits, `-` and `_`"
+                        ));
+                    }
+                    let prefix = format!("instances.{id}.");
+                    let crash_dir = crash_dir.join(&id);
+                    let instance = instance.validate(&prefix, defaults, crash_dir, problems);
+                    (id, Arc::new(instance))
+                })
+                .collect()
+        };
+
+        let default_instance = match self.default_instance {
+            Some(id) if !instances.contains_key(&id) => {
+                problems.push(format!("`default_instance` ({id:?}) is not an instance"));
+                None
+            }
+            Some(id) => Some(id),
+            None if instances.len() == 1 => instances.keys().next().cloned(),
+            None => None,
+        };
+
+        if self.max_running == Some(0) {
+            problems.push("`max_running` must be at least 1".to_string());
+        }
+        // a detached server could not be stopped gracefully once adopted, see `persist::adopt`.
+        if cfg!(windows) && self.detach_on_shutdown == Some(true) {
+            problems.push("`detach_on_shutdown` is not supported on windows".to_string());
+        }
+        if self.token.as_ref().is_some_and(String::is_empty) {
+            problems.push("`token` (`RUNNER_TOKEN`) is empty".to_string());
+        }
+
+        let mut file_filters = BTreeMap::new();
+        for (name, file) in self.filters {
+            match games::get(&name) {
+                Some(game) => {
+                    file_filters.insert(game.name(), (name, file));
+                }
+                None => problems.push(format!("`[filters.{name}]` is not a game")),
+            }
+        }
+        let mut filters = BTreeMap::new();
+        for game in games::all() {
+            let mut file = file_filters.remove(game.name());
+            for level in AuthLevel::ALL {
+                let rules = file.as_mut().and_then(|(name, file)| {
+                    let (key, rules) = file.take(level);
+                    Some((format!("filters.{name}.{key}"), rules?))
+                });
+                let filter = match rules {
+                    Some((prefix, rules)) => Filter::from_config(game, rules, &prefix, problems),
+                    None => Filter::default_for(game, level),
+                };
+                filters.insert((game.name(), level), filter);
+            }
+        }
+
+        let config = Config {
+            instances,
+            default_instance,
+            max_running: self.max_running,
+            port: self.port.unwrap_or(4321),
+            bind: self.bind.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+            allowed_ips: self.allowed_ips.unwrap_or_default(),
+            token: self.token,
+            show_console: self.show_console.unwrap_or(false),
+            steam_apikey: self.steam_apikey,
+            secret: self.secret,
+            filters,
+            state_file: self
+                .state_file
+                .unwrap_or_else(|| PathBuf::from("runner-state.toml")),
+            detach_on_shutdown: self.detach_on_shutdown.unwrap_or(false),
+            countdown_intervals,
+            crash: CrashConfig {
+                keep: self.crash.keep.unwrap_or(20),
+                console_lines,
+            },
+            helper: self.helper.map(|helper| helper.validate(problems)),
+        };
+
+        for instance in config.instances.values() {
+            if let Some(game) = games::get(&instance.server_type) {
+                problems.extend(game.config_problems(&config, instance));
+            }
+        }
+
+        config
+    }
+
+    fn unreloadable(old: &Config, new: &Config) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |changed: bool, name: &str| {
+            if changed {
+                problems.push(format!("`{name}` can only be changed by restarting"));
+            }
+        };
+
+        check(
+            !new.instances.keys().eq(old.instances.keys()),
+            "[instances]",
+        );
+        for (id, instance) in &new.instances {
+            if let Some(old) = old.instances.get(id) {
+                check(
+                    instance.server_dir != old.server_dir,
+                    &format!("instances.{id}.server_dir"),
+                );
+                check(
+                    instance.server_type != old.server_type,
+                    &format!("instances.{id}.server_type"),
+                );
+            }
+        }
+        check(
+            new.default_instance != old.default_instance,
+            "default_instance",
+        );
+        check(new.port != old.port, "port");
+        check(new.bind != old.bind, "bind");
+        check(new.state_file != old.state_file, "state_file");
+        check(new.helper.is_some() != old.helper.is_some(), "[helper]");
+
+        problems
+    }
+}
+
+/// the id of the only instance, when the config has no `[instances]`.
+pub const DEFAULT_INSTANCE: &str = "default";
+
+/// whether `id` can be used in a route and as a directory name.
+fn is_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl FileInstanceConfig {
+    /// `prefix` is where it is in the config, eg. `instances.mc.`, and `defaults` are the top-level `restart` and `stop`.
+    fn validate(
+        self,
+        prefix: &str,
+        defaults: (RestartConfig, StopConfig),
+        crash_dir: PathBuf,
+        problems: &mut Vec<String>,
+    ) -> InstanceConfig {
+        let server_dir = self.server_dir.unwrap_or_else(|| {
+            if prefix.is_empty() {
+                problems.push("`server_dir` (`SERVER_DIR`) is required".to_string());
+            } else {
+                problems.push(format!("`{prefix}server_dir` is required"));
+            }
+            PathBuf::new()
+        });
+        if !server_dir.as_os_str().is_empty() && !server_dir.is_dir() {
+            problems.push(format!(
+                "`{prefix}server_dir` ({}) is not a directory",
+                server_dir.display()
+            ));
+        }
+
+        let server_type = self.server_type.as_deref().unwrap_or("minecraft");
+        let game = games::get(server_type);
+        if game.is_none() {
+            let names: Vec<_> = games::names().map(|name| format!("`{name}`")).collect();
+            problems.push(format!(
+                "`{prefix}server_type` ({server_type:?}) must be one of {}",
+                names.join(", ")
+            ));
+        }
+
+        let custom = if game.is_some_and(|game| game.name() == "custom") {
+            if self.custom.is_none() {
+                problems.push(format!(
+                    "`[{prefix}custom]` is required for a custom server"
+                ));
+            }
+            Some(self.custom.unwrap_or_default().validate(prefix, problems))
+        } else {
+            None
+        };
+
+        let restart = self.restart.or(defaults.0);
+        let stop = self.stop.or(defaults.1);
+        if let (Some(backoff), Some(max_backoff)) = (restart.backoff, restart.max_backoff)
+            && backoff > max_backoff
+        {
+            problems.push(format!(
+                "`{prefix}restart.backoff` ({backoff}) is more than `{prefix}restart.max_backoff` ({max_backoff})"
+            ));
+        }
+        if stop.timeout == Some(0) {
+            problems.push(format!("`{prefix}stop.timeout` must be at least 1"));
+        }
+
+        InstanceConfig {
+            server_dir,
+            server_type: game
+                .map_or_else(|| server_type.to_string(), |game| game.name().to_string()),
+            game_args: self.game_args.unwrap_or_default(),
+            crash_dir,
+            restart,
+            stop,
+            custom,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use common::{AuthLevel, config::FileConfig as _};
+
+    use super::{CustomStop, DEFAULT_INSTANCE, FileConfig};
+    use crate::games::Minecraft;
+
+    #[test]
+    fn validate() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            server_type = "factorio"
+            countdown_intervals = [5, 60, 5]
+
+            [restart]
+            backoff = 60
+            max_backoff = 10
+
+            [helper]
+            url = "not a url"
+            runner = "pc"
+            "#,
+        )
+        .unwrap();
+
+        let mut problems = Vec::new();
+        let config = file.validate(&mut problems);
+        // no `server_dir`, bad `server_type`, `backoff` over `max_backoff`, bad `helper.url`, no `helper.token`.
+        assert_eq!(problems.len(), 5, "{problems:#?}");
+        assert_eq!(config.countdown_intervals, [60, 5]);
+
+        assert!(toml::from_str::<FileConfig>("sever_dir = 'typo'").is_err());
+    }
+
+    #[test]
+    fn custom() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            server_dir = "."
+            server_type = "custom"
+
+            [custom]
+            executable = "VintagestoryServer"
+            stop = { command = "/stop" }
+            ready = "Dedicated Server now running"
+            "#,
+        )
+        .unwrap();
+        let mut problems = Vec::new();
+        let config = file.validate(&mut problems);
+        assert!(problems.is_empty(), "{problems:#?}");
+        assert!(matches!(
+            &config.instances[DEFAULT_INSTANCE].custom.as_ref().unwrap().stop,
+            CustomStop::Command(command) if command == "/stop"
+        ));
+
+        // no executable, and no way to tell when it is ready.
+        let file: FileConfig =
+            toml::from_str("server_dir = '.'\nserver_type = 'custom'\n[custom]").unwrap();
+        let mut problems = Vec::new();
+        file.validate(&mut problems);
+        assert_eq!(problems.len(), 2, "{problems:#?}");
+    }
+
+    #[test]
+    fn instances() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            default_instance = "mc"
+
+            [restart]
+            max = 3
+
+            [instances.mc]
+            server_dir = "."
+
+            [instances.tml]
+            server_dir = "."
+            server_type = "Terraria"
+            restart = { max = 1 }
+            "#,
+        )
+        .unwrap();
+        let mut problems = Vec::new();
+        let config = file.validate(&mut problems);
+        // terraria needs `steam_apikey`.
+        assert_eq!(problems.len(), 1, "{problems:#?}");
+        assert_eq!(config.default_instance.as_deref(), Some("mc"));
+
+        let tml = &config.instances["tml"];
+        assert_eq!(tml.server_type, "terraria");
+        assert_eq!(tml.restart.max, Some(1));
+        assert_eq!(tml.crash_dir, Path::new("crashes/tml"));
+        assert_eq!(config.instances["mc"].restart.max, Some(3));
+
+        // a flat `server_dir` with instances, a bad id, and an unknown default.
+        let file: FileConfig = toml::from_str(
+            "server_dir = '.'\ndefault_instance = 'x'\n[instances.'a b']\nserver_dir = '.'",
+        )
+        .unwrap();
+        let mut problems = Vec::new();
+        file.validate(&mut problems);
+        assert_eq!(problems.len(), 3, "{problems:#?}");
+    }
+
+    #[test]
+    fn filters() {
+        let file: FileConfig = toml::from_str(
+            r#"
+            server_dir = "."
+
+            [filters.Minecraft]
+            basic = [{ action = "drop", regex = "." }]
+
+            [filters.factorio]
+            "#,
+        )
+        .unwrap();
+        let mut problems = Vec::new();
+        let config = file.validate(&mut problems);
+        // `factorio` is not a game.
+        assert_eq!(problems.len(), 1, "{problems:#?}");
+
+        let line = "[12:34:56 INFO]: Steve[/192.168.1.23:51234] logged in";
+        let filter = |level| config.filter(&Minecraft, level).apply(line);
+        assert_eq!(filter(AuthLevel::Basic), None);
+        assert_eq!(
+            filter(AuthLevel::Unauthed).as_deref(),
+            Some("[12:34:56 INFO]: Steve[/***] logged in")
+        );
+        assert_eq!(filter(AuthLevel::Stop).as_deref(), Some(line));
+    }
+}
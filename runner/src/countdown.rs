@@ -0,0 +1,86 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{AppState, config::config};
+
+/// the seconds left at which to announce, largest first, always including `delay` itself.
+fn schedule(delay: u64, intervals: &[u64]) -> Vec<u64> {
+    let mut schedule = vec![delay];
+    schedule.extend(intervals.iter().copied().filter(|&i| i < delay && i > 0));
+    schedule
+}
+
+fn format_secs(secs: u64) -> String {
+    match secs {
+        1 => "1 second".to_string(),
+        s if s % 60 == 0 && s > 60 => format!("{} minutes", s / 60),
+        60 => "1 minute".to_string(),
+        s => format!("{s} seconds"),
+    }
+}
+
+/// counts down `delay` in the background, then runs `then`.
+///
+/// returns false if there is already a countdown.
+pub fn spawn<F: Future<Output = ()> + Send + 'static>(
+    state: &Arc<AppState>,
+    action: &'static str,
+    delay: Duration,
+    then: impl FnOnce(Arc<AppState>) -> F + Send + 'static,
+) -> bool {
+    let mut pending = state.countdown.lock().expect("not poisoned");
+    if pending.as_ref().is_some_and(|task| !task.is_finished()) {
+        return false;
+    }
+
+    tracing::info!("server {action} in {delay:?}");
+    let state = state.clone();
+    let task = tokio::spawn(async move {
+        countdown(&state, action, delay).await;
+        then(state).await;
+    });
+    pending.replace(task.abort_handle());
+
+    true
+}
+
+/// cancels the running countdown, returning whether there was one.
+pub fn cancel(state: &AppState) -> bool {
+    let pending = state.countdown.lock().expect("not poisoned").take();
+    pending.is_some_and(|task| {
+        let cancelled = !task.is_finished();
+        task.abort();
+        cancelled
+    })
+}
+
+/// announces in-game that the server is `action` (eg. `stopping`) at every interval, returning once `delay` has passed.
+async fn countdown(state: &Arc<AppState>, action: &str, delay: Duration) {
+    let schedule = schedule(delay.as_secs(), &config().countdown_intervals);
+    for (i, &left) in schedule.iter().enumerate() {
+        let msg = format!("server {action} in {}!", format_secs(left));
+        if let Err(err) = state.game.announce(state, &msg) {
+            tracing::debug!("could not announce: {err}");
+        }
+
+        let next = schedule.get(i + 1).copied().unwrap_or(0);
+        tokio::time::sleep(Duration::from_secs(left - next)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_secs, schedule};
+
+    #[test]
+    fn countdown_schedule() {
+        let intervals = [300, 60, 30, 10, 5, 1];
+        assert_eq!(schedule(45, &intervals), [45, 30, 10, 5, 1]);
+        assert_eq!(schedule(60, &intervals), [60, 30, 10, 5, 1]);
+        assert_eq!(schedule(1, &intervals), [1]);
+
+        assert_eq!(format_secs(300), "5 minutes");
+        assert_eq!(format_secs(60), "1 minute");
+        assert_eq!(format_secs(90), "90 seconds");
+        assert_eq!(format_secs(1), "1 second");
+    }
+}
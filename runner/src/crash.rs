@@ -0,0 +1,286 @@
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+const CONSOLE_FILE: &str = "console.log";
+
+/// the last lines of the server console, so they can be saved if it crashes.
+#[derive(Debug)]
+pub struct ConsoleTail {
+    lines: Mutex<VecDeque<String>>,
+    capacity: AtomicUsize,
+}
+
+impl ConsoleTail {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity: AtomicUsize::new(capacity),
+        }
+    }
+
+    /// keep the last `capacity` lines from now on.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+    }
+
+    pub fn push(&self, line: String) {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        let mut lines = self.lines.lock().expect("not poisoned");
+        while lines.len() >= capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub fn clear(&self) {
+        self.lines.lock().expect("not poisoned").clear();
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrashReport {
+    pub id: String,
+    pub at: SystemTime,
+    pub files: Vec<String>,
+}
+
+/// the newest file in `dir` modified after `since` whose name matches `pred`.
+pub fn newest_file(dir: &Path, since: SystemTime, pred: impl Fn(&str) -> bool) -> Option<PathBuf> {
+    files_since(dir, since, pred)
+        .into_iter()
+        .max_by_key(|(_, modified)| *modified)
+        .map(|(path, _)| path)
+}
+
+/// every file in `dir` modified after `since` whose name matches `pred`.
+pub fn files_since(
+    dir: &Path,
+    since: SystemTime,
+    pred: impl Fn(&str) -> bool,
+) -> Vec<(PathBuf, SystemTime)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name().to_str().is_some_and(&pred))
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            let modified = meta.modified().ok()?;
+            (meta.is_file() && modified >= since).then(|| (entry.path(), modified))
+        })
+        .collect()
+}
+
+/// saves `artifacts` and the `console` lines as a new crash report in `dir`, returning its id.
+///
+/// then deletes the oldest reports, keeping `keep`.
+///
+/// # Errors
+///
+/// Will error if the report directory or console log could not be written.
+/// artifacts that could not be copied are skipped.
+pub fn capture(
+    dir: &Path,
+    artifacts: &[PathBuf],
+    console: &[String],
+    status: &str,
+    keep: usize,
+) -> anyhow::Result<String> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let reports = dir;
+    fs::create_dir_all(reports).with_context(|| format!("creating {reports:?}"))?;
+
+    // reports in the same second get a suffix, eg. `1700000000-1`.
+    let mut n = 0;
+    let (id, dir) = loop {
+        let id = if n == 0 {
+            secs.to_string()
+        } else {
+            format!("{secs}-{n}")
+        };
+        let dir = reports.join(&id);
+        match fs::create_dir(&dir) {
+            Ok(()) => break (id, dir),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            Err(err) => return Err(err).with_context(|| format!("creating {dir:?}")),
+        }
+    };
+
+    let mut log = format!("# server exited: {status}\n");
+    for line in console {
+        log.push_str(line);
+        log.push('\n');
+    }
+    fs::write(dir.join(CONSOLE_FILE), log).context("writing console log")?;
+
+    for artifact in artifacts {
+        let Some(name) = artifact.file_name() else {
+            continue;
+        };
+        if let Err(err) = fs::copy(artifact, dir.join(name)) {
+            tracing::warn!("could not copy {artifact:?}: {err}");
+        }
+    }
+
+    prune(reports, keep);
+
+    Ok(id)
+}
+
+/// deletes the oldest reports in `dir`, keeping `keep`.
+fn prune(dir: &Path, keep: usize) {
+    let reports = list(dir);
+    for report in reports.iter().skip(keep) {
+        if let Err(err) = fs::remove_dir_all(dir.join(&report.id)) {
+            tracing::warn!("could not delete crash report {}: {err}", report.id);
+        }
+    }
+}
+
+/// every crash report saved in `dir`, newest first.
+pub fn list(dir: &Path) -> Vec<CrashReport> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut reports: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let id = entry.file_name().into_string().ok()?;
+            let (secs, n) = match id.split_once('-') {
+                Some((secs, n)) => (secs.parse().ok()?, n.parse().ok()?),
+                None => (id.parse().ok()?, 0),
+            };
+
+            let mut files: Vec<_> = fs::read_dir(entry.path())
+                .ok()?
+                .filter_map(Result::ok)
+                .filter_map(|file| file.file_name().into_string().ok())
+                .collect();
+            files.sort();
+
+            Some((
+                n,
+                CrashReport {
+                    id,
+                    at: UNIX_EPOCH + Duration::from_secs(secs),
+                    files,
+                },
+            ))
+        })
+        .collect();
+    reports.sort_by_key(|(n, report)| std::cmp::Reverse((report.at, *n)));
+
+    reports.into_iter().map(|(_, report)| report).collect()
+}
+
+/// the path of `file` in the report `id` in `dir`, if both are plain names.
+pub fn file_path(dir: &Path, id: &str, file: &str) -> Option<PathBuf> {
+    (is_name(id) && is_name(file)).then(|| dir.join(id).join(file))
+}
+
+/// whether `name` is a single normal path component, so it can't escape its directory.
+fn is_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(std::path::Component::Normal(_)), None)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, fs, process, slice};
+
+    use super::{ConsoleTail, capture, is_name, list};
+
+    #[test]
+    fn console_tail() {
+        let tail = ConsoleTail::new(2);
+        for line in ["a", "b", "c"] {
+            tail.push(line.to_string());
+        }
+        assert_eq!(tail.lines(), ["b", "c"]);
+
+        tail.set_capacity(1);
+        tail.push("d".to_string());
+        assert_eq!(tail.lines(), ["d"]);
+    }
+
+    #[test]
+    fn no_traversal() {
+        assert!(is_name("console.log"));
+        assert!(!is_name(".."));
+        assert!(!is_name("../../secret"));
+        assert!(!is_name("/etc/passwd"));
+        assert!(!is_name(""));
+    }
+
+    #[test]
+    fn capture_prune_list() {
+        let dir = std::env::temp_dir().join(format!("runner-crashes-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let artifact = std::env::temp_dir().join(format!("runner-crash-{}.txt", process::id()));
+        fs::write(&artifact, "boom").unwrap();
+
+        let console = ["a".to_string(), "b".to_string()];
+        let ids: Vec<_> = (0..3)
+            .map(|_| {
+                capture(
+                    &dir,
+                    slice::from_ref(&artifact),
+                    &console,
+                    "exit status: 1",
+                    2,
+                )
+                .unwrap()
+            })
+            .collect();
+        // most likely in the same second, but none overwritten.
+        assert_eq!(ids.iter().collect::<BTreeSet<_>>().len(), 3);
+
+        // the oldest was pruned.
+        let reports = list(&dir);
+        let listed: Vec<_> = reports.iter().map(|r| r.id.clone()).collect();
+        let mut newest = ids[1..].to_vec();
+        newest.reverse();
+        assert_eq!(listed, newest);
+        assert_eq!(
+            reports[0].files,
+            [
+                "console.log".to_string(),
+                artifact.file_name().unwrap().to_str().unwrap().to_string()
+            ]
+        );
+        let log = fs::read_to_string(dir.join(&ids[2]).join("console.log")).unwrap();
+        assert_eq!(log, "# server exited: exit status: 1\na\nb\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+        fs::remove_file(&artifact).unwrap();
+    }
+}
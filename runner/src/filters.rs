@@ -0,0 +1,299 @@
+use common::AuthLevel;
+use regex::{NoExpand, Regex};
+use serde::Deserialize;
+
+use crate::games::Game;
+
+/// what redacted text is replaced with, if a rule doesn't set `with`.
+const REDACTED: &str = "***";
+
+/// regexes any game's rules can use by name, which are redacted by default.
+const PATTERNS: &[(&str, &str)] = &[
+    ("ipv4", r"\b(?:\d{1,3}\.){3}\d{1,3}(?::\d{1,5})?\b"),
+    (
+        "ipv6",
+        r"(?i)\b(?:[0-9a-f]{1,4}:){7}[0-9a-f]{1,4}\b|\b(?:[0-9a-f]{1,4}:){1,7}:(?:[0-9a-f]{1,4}(?::[0-9a-f]{1,4})*)?|::[0-9a-f]{1,4}(?::[0-9a-f]{1,4})*\b",
+    ),
+    (
+        "uuid",
+        r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b",
+    ),
+    // eg. `100.5, 64.0, -200.25`.
+    (
+        "coords",
+        r"-?\d+(?:\.\d+)?,\s*-?\d+(?:\.\d+)?,\s*-?\d+(?:\.\d+)?",
+    ),
+];
+
+/// the ordered rules a console line goes through before it is shown to one [`AuthLevel`].
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    regex: Regex,
+    action: Action,
+}
+
+#[derive(Debug, Clone)]
+enum Action {
+    /// replaces every match.
+    Redact(String),
+    /// shows the line as it is, without the rules after this one.
+    Pass,
+    /// hides the line.
+    Drop,
+}
+
+/// a rule in `[filters.<game>]`, with either a named `pattern` or a `regex`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    action: ActionConfig,
+    /// one of [`PATTERNS`], or the game's [`Game::line_patterns`].
+    pattern: Option<String>,
+    regex: Option<String>,
+    /// what `redact` replaces matches with.
+    with: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ActionConfig {
+    Redact,
+    Pass,
+    Drop,
+}
+
+impl Filter {
+    /// the line as `level` should see it, `None` if it is dropped.
+    pub fn apply(&self, line: &str) -> Option<String> {
+        let mut line = line.to_string();
+        for rule in &self.rules {
+            if !rule.regex.is_match(&line) {
+                continue;
+            }
+            match &rule.action {
+                Action::Redact(with) => {
+                    line = rule.regex.replace_all(&line, NoExpand(with)).into_owned();
+                }
+                Action::Pass => break,
+                Action::Drop => return None,
+            }
+        }
+        Some(line)
+    }
+
+    /// used when `level` has no rules for `game` in the config.
+    ///
+    /// `stop` sees everything, everyone else sees the game's [`Game::line_patterns`] as they are, and every other line
+    /// with [`PATTERNS`] redacted.
+    pub fn default_for(game: &dyn Game, level: AuthLevel) -> Self {
+        if level == AuthLevel::Stop {
+            return Self::default();
+        }
+
+        let pass = game
+            .line_patterns()
+            .iter()
+            .map(|&(_, regex)| (regex, Action::Pass));
+        let redact = PATTERNS
+            .iter()
+            .map(|&(_, regex)| (regex, Action::Redact(REDACTED.to_string())));
+        let rules = pass
+            .chain(redact)
+            .map(|(regex, action)| Rule {
+                regex: Regex::new(regex).expect("valid regex"),
+                action,
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    /// `prefix` is where the rules are in the config, eg. `filters.minecraft.basic`.
+    pub fn from_config(
+        game: &dyn Game,
+        rules: Vec<RuleConfig>,
+        prefix: &str,
+        problems: &mut Vec<String>,
+    ) -> Self {
+        let rules = rules
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, rule)| rule.compile(game, &format!("{prefix}[{i}]"), problems))
+            .collect();
+
+        Self { rules }
+    }
+}
+
+impl RuleConfig {
+    fn compile(self, game: &dyn Game, at: &str, problems: &mut Vec<String>) -> Option<Rule> {
+        let regex = match (self.pattern, self.regex) {
+            (Some(name), None) => {
+                let Some(&(_, regex)) = PATTERNS
+                    .iter()
+                    .chain(game.line_patterns())
+                    .find(|(pattern, _)| *pattern == name)
+                else {
+                    problems.push(format!(
+                        "`{at}.pattern` ({name:?}) is not a pattern of {}",
+                        game.name()
+                    ));
+                    return None;
+                };
+                Regex::new(regex).expect("valid regex")
+            }
+            (None, Some(regex)) => match Regex::new(&regex) {
+                Ok(regex) => regex,
+                Err(err) => {
+                    problems.push(format!("`{at}.regex`: {err}"));
+                    return None;
+                }
+            },
+            _ => {
+                problems.push(format!("`{at}` needs one of `pattern` or `regex`"));
+                return None;
+            }
+        };
+
+        let action = match (self.action, self.with) {
+            (ActionConfig::Redact, with) => {
+                Action::Redact(with.unwrap_or_else(|| REDACTED.to_string()))
+            }
+            (ActionConfig::Pass, None) => Action::Pass,
+            (ActionConfig::Drop, None) => Action::Drop,
+            (_, Some(_)) => {
+                problems.push(format!("`{at}.with` is only used by `redact`"));
+                return None;
+            }
+        };
+
+        Some(Rule { regex, action })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::AuthLevel;
+    use serde::Deserialize;
+
+    use super::{Filter, RuleConfig};
+    use crate::games::{Minecraft, Terraria};
+
+    #[test]
+    fn defaults() {
+        let filter = Filter::default_for(&Minecraft, AuthLevel::Unauthed);
+
+        // paper
+        assert_eq!(
+            filter
+                .apply("[12:34:56 INFO]: Steve[/192.168.1.23:51234] logged in with entity id 123 at ([world]100.5, 64.0, -200.25)")
+                .unwrap(),
+            "[12:34:56 INFO]: Steve[/***] logged in with entity id 123 at ([world]***)"
+        );
+        assert_eq!(
+            filter
+                .apply(
+                    "[12:34:56 INFO]: UUID of player Steve is 069a79f4-44e9-4726-a5be-fca90e38aaf5"
+                )
+                .unwrap(),
+            "[12:34:56 INFO]: UUID of player Steve is ***"
+        );
+        let list = "[12:34:56 INFO]: There are 1 of a max of 20 players online: Steve";
+        assert_eq!(filter.apply(list).unwrap(), list);
+
+        // forge
+        assert_eq!(
+            filter
+                .apply("[12:34:56] [Server thread/INFO] [minecraft/PlayerList]: Steve[/10.0.0.5:60000] logged in with entity id 187 at (12.5, 70.0, -33.5)")
+                .unwrap(),
+            "[12:34:56] [Server thread/INFO] [minecraft/PlayerList]: Steve[/***] logged in with entity id 187 at (***)"
+        );
+        let list = "[12:34:56] [Server thread/INFO] [minecraft/DedicatedServer]: There are 0 of a max of 20 players online: ";
+        assert_eq!(filter.apply(list).unwrap(), list);
+        let done = r#"[12:34:56] [Server thread/INFO] [minecraft/DedicatedServer]: Done (12.345s)! For help, type "help""#;
+        assert_eq!(filter.apply(done).unwrap(), done);
+
+        // tmodloader
+        let filter = Filter::default_for(&Terraria, AuthLevel::Basic);
+        assert_eq!(
+            filter
+                .apply("[12:34:56.789] [Server/INFO] [tML]: 192.168.1.23:51234 is connecting...")
+                .unwrap(),
+            "[12:34:56.789] [Server/INFO] [tML]: *** is connecting..."
+        );
+        assert_eq!(
+            filter
+                .apply("[12:34:56.789] [Server/INFO] [tML]: [::1]:61234 is connecting...")
+                .unwrap(),
+            "[12:34:56.789] [Server/INFO] [tML]: [***]:61234 is connecting..."
+        );
+        let version = "[12:34:56.789] [Main Thread/INFO] [tML]: tModLoader v2024.5.3.0 Linux-x64";
+        assert_eq!(filter.apply(version).unwrap(), version);
+
+        let line = "[12:34:56 INFO]: Steve[/192.168.1.23:51234] logged in";
+        assert_eq!(
+            Filter::default_for(&Minecraft, AuthLevel::Stop)
+                .apply(line)
+                .unwrap(),
+            line
+        );
+    }
+
+    #[test]
+    fn rules() {
+        #[derive(Deserialize)]
+        struct Rules {
+            rules: Vec<RuleConfig>,
+        }
+        let Rules { rules } = toml::from_str(
+            r#"
+            rules = [
+                { action = "drop", regex = 'issued server command' },
+                { action = "pass", pattern = "list" },
+                { action = "redact", pattern = "ipv4", with = "$ip" },
+                { action = "redact", regex = '\d', with = "*" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let mut problems = Vec::new();
+        let filter =
+            Filter::from_config(&Minecraft, rules, "filters.minecraft.basic", &mut problems);
+        assert!(problems.is_empty(), "{problems:#?}");
+
+        assert_eq!(
+            filter.apply("[12:34:56 INFO]: Steve issued server command: /tp 0 64 0"),
+            None
+        );
+        let list = "[12:34:56 INFO]: There are 1 of a max of 20 players online: Steve";
+        assert_eq!(filter.apply(list).unwrap(), list);
+        // rules apply in order, so the digits of the ip are already gone.
+        assert_eq!(
+            filter
+                .apply("[12:34:56 INFO]: Steve[/192.168.1.23:51234] logged in")
+                .unwrap(),
+            "[**:**:** INFO]: Steve[/$ip] logged in"
+        );
+
+        // a pattern of another game, both `pattern` and `regex`, `with` on `drop`, and a bad regex.
+        let Rules { rules } = toml::from_str(
+            r#"
+            rules = [
+                { action = "pass", pattern = "list" },
+                { action = "drop", pattern = "uuid", regex = "x" },
+                { action = "drop", regex = "x", with = "y" },
+                { action = "drop", regex = "(" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let mut problems = Vec::new();
+        Filter::from_config(&Terraria, rules, "filters.terraria.unauthed", &mut problems);
+        assert_eq!(problems.len(), 4, "{problems:#?}");
+    }
+}
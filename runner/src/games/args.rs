@@ -0,0 +1,156 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Deserializer};
+
+/// the args given to the game server, from `game_args`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GameArgs(Vec<String>);
+
+impl GameArgs {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    /// the value of `name`, given as `name=value` or `name value`, ignoring case.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        let mut args = self.iter();
+        while let Some(arg) = args.next() {
+            if arg.eq_ignore_ascii_case(name) {
+                return args.next();
+            }
+            if let Some((key, value)) = arg.split_once('=')
+                && key.eq_ignore_ascii_case(name)
+            {
+                return Some(value);
+            }
+        }
+
+        None
+    }
+}
+
+/// splits on whitespace, except inside `"` or `'` quotes. backslashes are kept as is, so windows paths work.
+impl FromStr for GameArgs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut args = Vec::new();
+        // `Some` once an arg has started, so `""` is an empty arg.
+        let mut arg: Option<String> = None;
+        let mut quote = None;
+
+        for char in s.chars() {
+            match quote {
+                Some(q) if char == q => quote = None,
+                Some(_) => arg.get_or_insert_default().push(char),
+                None if char == '"' || char == '\'' => {
+                    quote = Some(char);
+                    arg.get_or_insert_default();
+                }
+                None if char.is_whitespace() => args.extend(arg.take()),
+                None => arg.get_or_insert_default().push(char),
+            }
+        }
+
+        if let Some(quote) = quote {
+            return Err(format!("unclosed {quote}"));
+        }
+        args.extend(arg);
+
+        // they used to be separated by backslashes.
+        if args.iter().any(|arg| arg.contains("\\-")) {
+            return Err("args are separated by spaces, not `\\`".to_string());
+        }
+
+        Ok(Self(args))
+    }
+}
+
+/// either a list of args, or a string to be split by [`GameArgs::from_str`].
+impl<'de> Deserialize<'de> for GameArgs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            List(Vec<String>),
+            Line(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::List(args) => Ok(Self(args)),
+            Raw::Line(line) => line.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// the value of `key` in a file of `key=value` lines, like `server.properties`.
+pub fn property<'a>(contents: &'a str, key: &str) -> Option<&'a str> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| {
+            let (k, value) = line.split_once('=')?;
+            (k.trim() == key).then(|| value.trim())
+        })
+}
+
+/// parses `value`, or warns and returns `default` if it is missing or invalid.
+pub fn parse_or<T: FromStr + Display>(name: &str, value: Option<&str>, default: T) -> T {
+    if let Some(value) = value.and_then(|v| v.parse().ok()) {
+        value
+    } else {
+        tracing::warn!("using default {name} {default}");
+        default
+    }
+}
+
+/// the value of `name` in `args`, or `default`.
+pub fn arg_or<T: FromStr + Display>(args: &GameArgs, name: &str, default: T) -> T {
+    parse_or(name, args.value(name), default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameArgs, property};
+
+    #[test]
+    fn parse() {
+        let args: GameArgs = r#"-Xmx4G -config "C:\My Server\config.txt" -motd 'hi there' """#
+            .parse()
+            .unwrap();
+        assert_eq!(
+            args.iter().collect::<Vec<_>>(),
+            [
+                "-Xmx4G",
+                "-config",
+                r"C:\My Server\config.txt",
+                "-motd",
+                "hi there",
+                ""
+            ]
+        );
+
+        assert!("-world 'unclosed".parse::<GameArgs>().is_err());
+        assert!(r"-Xmx4G\-Xms1G".parse::<GameArgs>().is_err());
+    }
+
+    #[test]
+    fn value() {
+        let args: GameArgs = "-port 7777 -Port=7778 -log".parse().unwrap();
+        assert_eq!(args.value("-port"), Some("7777"));
+        assert_eq!(args.value("-log"), None);
+        assert_eq!(args.value("-world"), None);
+
+        let args: GameArgs = "-Port=7778".parse().unwrap();
+        assert_eq!(args.value("-port"), Some("7778"));
+
+        let properties = "#comment\nquery.port=1\nserver-port = 25566\n";
+        assert_eq!(property(properties, "server-port"), Some("25566"));
+        assert_eq!(property(properties, "port"), None);
+    }
+}
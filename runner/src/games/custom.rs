@@ -0,0 +1,127 @@
+use std::{
+    process::Stdio,
+    sync::{Arc, atomic::Ordering},
+    time::SystemTime,
+};
+
+use anyhow::anyhow;
+use reqwest::Client;
+use tokio::process::Command;
+
+use super::{GameServer, Readiness, RunResult, Variant, own_process_group};
+use crate::{
+    AppState, ServerInfo,
+    config::{CustomConfig, CustomStop, InstanceConfig, StopSignal},
+};
+
+/// a game declared by an instance's `[custom]` in the config.
+pub struct Custom;
+
+#[derive(Debug, Clone)]
+pub enum ServerType {
+    Custom,
+}
+
+/// only `None` if the instance is not a custom server, which it must be to get here.
+fn custom_config(instance: &InstanceConfig) -> &CustomConfig {
+    instance
+        .custom
+        .as_ref()
+        .expect("`[custom]` is validated when `server_type` is `custom`")
+}
+
+impl GameServer for Custom {
+    type Variant = ServerType;
+    const NAME: &'static str = "custom";
+
+    fn spawn(instance: &InstanceConfig, _variant: ServerType) -> RunResult {
+        let server_path = &instance.server_dir;
+        let custom = custom_config(instance);
+
+        let exe = server_path.join(&custom.executable);
+        let exe = if exe.exists() {
+            exe
+        } else {
+            custom.executable.clone()
+        };
+
+        let mut cmd = Command::new(exe);
+        cmd.args(custom.args.iter())
+            .args(instance.game_args.iter())
+            .stdout(Stdio::piped())
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .current_dir(server_path.join(&custom.working_dir));
+
+        // so an interrupt only reaches the server.
+        #[cfg(windows)]
+        {
+            use windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP;
+            cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+        }
+
+        Ok(own_process_group(&mut cmd).spawn())
+    }
+
+    fn stop(state: Arc<AppState>) -> anyhow::Result<()> {
+        let stop = custom_config(&state.config()).stop.clone();
+        match stop {
+            CustomStop::Command(command) => {
+                if let Err(err) = state.server_stdin.send(command) {
+                    return Err(anyhow!("failed to send stop command: {err}"));
+                }
+            }
+            CustomStop::Signal(signal) => {
+                let pid = state.server_pid.load(Ordering::Relaxed);
+                if pid == 0 {
+                    return Err(anyhow!("tried to stop but pid was 0"));
+                }
+
+                let res = match signal {
+                    StopSignal::Interrupt => runner::interrupt(pid),
+                    StopSignal::Terminate => runner::terminate(pid),
+                };
+                res.map_err(|err| anyhow!("failed to signal pid {pid}: {err}"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn list_command(instance: &InstanceConfig) -> Option<String> {
+        custom_config(instance).list_command.clone()
+    }
+
+    fn readiness(instance: &InstanceConfig) -> Readiness {
+        let custom = custom_config(instance);
+        match (&custom.ready, custom.port) {
+            (Some(regex), _) => Readiness::Console(regex.clone()),
+            (None, Some(port)) => Readiness::Port(port),
+            (None, None) => unreachable!("validated to have `ready` or `port`"),
+        }
+    }
+
+    async fn server_info(
+        _client: &Client,
+        instance: &InstanceConfig,
+        start_time: SystemTime,
+        _variant: ServerType,
+    ) -> anyhow::Result<ServerInfo> {
+        let custom = custom_config(instance);
+
+        Ok(ServerInfo {
+            port: custom.port.unwrap_or_default().into(),
+            start_time,
+            version: custom.name.clone(),
+            mods: vec![],
+            startup_duration: None,
+        })
+    }
+}
+
+impl Variant for ServerType {
+    fn detect(_instance: &InstanceConfig) -> Option<Self> {
+        // there is nothing to detect, the config says what it is.
+        Some(Self::Custom)
+    }
+}
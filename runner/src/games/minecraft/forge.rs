@@ -1,6 +1,5 @@
 use std::{
     borrow::Cow,
-    env,
     path::{Path, PathBuf},
     time::SystemTime,
 };
@@ -10,7 +9,7 @@ use reqwest::Client;
 use serde::Deserialize;
 
 use super::meta::{self, ModMeta, extract_jar};
-use crate::{ServerInfo, games::ARG_SEP};
+use crate::{ServerInfo, games::GameArgs};
 
 pub fn find_platform_args(server_path: &Path) -> anyhow::Result<PathBuf> {
     let forge_dir = server_path.join("libraries/net/minecraftforge/forge/");
@@ -31,13 +30,13 @@ pub fn find_platform_args(server_path: &Path) -> anyhow::Result<PathBuf> {
         .join(args_file))
 }
 
-pub fn args(server_path: &Path) -> Result<Vec<String>, &'static str> {
+pub fn args(server_path: &Path, game_args: &GameArgs) -> Result<Vec<String>, &'static str> {
     let mut args = Vec::new();
 
     if server_path.join("user_jvm_args.txt").exists() {
         args.push("@user_jvm_args.txt".to_string());
-    } else if let Ok(jvm_args) = env::var("GAME_ARGS") {
-        args.extend(jvm_args.trim().split(ARG_SEP).map(ToString::to_string));
+    } else if !game_args.is_empty() {
+        args.extend(game_args.iter().map(ToString::to_string));
     } else {
         tracing::warn!("could not find `user_jvm_args.txt` file");
     }
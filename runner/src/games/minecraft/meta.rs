@@ -106,10 +106,11 @@ pub async fn get_info_mods(
     }
 
     Ok(ServerInfo {
-        port: super::vanilla::port(server_path).unwrap_or(25565),
+        port: super::vanilla::port(server_path),
         start_time,
         version,
         mods,
+        startup_duration: None,
     })
 }
 
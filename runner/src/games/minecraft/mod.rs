@@ -1,11 +1,20 @@
 use anyhow::anyhow;
 use axum::http::StatusCode;
+use common::{Capabilities, Progress};
+use regex::Regex;
 use reqwest::Client;
-use std::{path::Path, process::Stdio, sync::Arc, time::SystemTime};
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 use tokio::process::Command;
 
-use super::{GameServer, RunResult, Variant};
-use crate::{AppState, ServerInfo};
+use super::{GameServer, Readiness, RunResult, Variant, own_process_group, parse_percent};
+use crate::{
+    AppState, ServerInfo, config::InstanceConfig, crash::newest_file, stopping::StopPolicy,
+};
 
 mod meta;
 mod modrinth;
@@ -23,12 +32,17 @@ pub enum ServerType {
     Vanilla,
 }
 
-impl GameServer<ServerType> for Minecraft {
-    fn spawn(server_path: &Path, variant: ServerType) -> RunResult {
+impl GameServer for Minecraft {
+    type Variant = ServerType;
+    const NAME: &'static str = "minecraft";
+
+    fn spawn(instance: &InstanceConfig, variant: ServerType) -> RunResult {
+        let server_path = &instance.server_dir;
+        let game_args = &instance.game_args;
         let args = match variant {
-            ServerType::Forge => forge::args(server_path),
-            ServerType::Paper => paper::args(server_path),
-            ServerType::Vanilla => vanilla::args(server_path),
+            ServerType::Forge => forge::args(server_path, game_args),
+            ServerType::Paper => paper::args(server_path, game_args),
+            ServerType::Vanilla => vanilla::args(server_path, game_args),
         };
 
         let args = match args {
@@ -36,13 +50,14 @@ impl GameServer<ServerType> for Minecraft {
             Err(err) => return Err((StatusCode::INTERNAL_SERVER_ERROR, err)),
         };
 
-        let child = Command::new("java")
-            .args(args)
+        let mut cmd = Command::new("java");
+        cmd.args(args)
             .stdout(Stdio::piped())
             .stdin(Stdio::piped())
             .stderr(Stdio::piped())
-            .current_dir(server_path)
-            .spawn();
+            .current_dir(server_path);
+
+        let child = own_process_group(&mut cmd).spawn();
 
         Ok(child)
     }
@@ -55,12 +70,107 @@ impl GameServer<ServerType> for Minecraft {
         }
     }
 
+    fn announce(state: &AppState, msg: &str) -> anyhow::Result<()> {
+        if let Err(err) = state.server_stdin.send(format!("/say {msg}")) {
+            Err(anyhow!("failed to send `/say`: {err}"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn line_patterns() -> &'static [(&'static str, &'static str)] {
+        // from `/list`, eg. `[12:34:56 INFO]: There are` on paper,
+        // `[12:34:56] [Server thread/INFO] [minecraft/MinecraftServer]: There are` on forge.
+        &[(
+            "list",
+            r"^\[[^\]]*\](?: \[[^\]]*\]){0,2}: There are \d+ of a max of \d+ players online",
+        )]
+    }
+
+    fn log_setup(_instance: &InstanceConfig) {
+        let java_version = match std::process::Command::new("java").arg("--version").output() {
+            Ok(output) => output,
+            Err(err) => {
+                tracing::error!("`java --version` failed to run: {err}");
+                return;
+            }
+        };
+
+        tracing::info!("");
+        for line in String::from_utf8_lossy(&java_version.stdout).lines() {
+            if !line.is_empty() {
+                tracing::info!("{line}");
+            }
+        }
+    }
+
+    fn stop_policy() -> StopPolicy {
+        StopPolicy {
+            // `[12:34:56] [Server thread/INFO]: ThreadedAnvilChunkStorage: All dimensions are saved`
+            saved: Some(Regex::new(r"All dimensions are saved").expect("valid regex")),
+            // a large modded world can take minutes to save.
+            save_timeout: Duration::from_secs(5 * 60),
+            ..StopPolicy::default()
+        }
+    }
+
+    fn capabilities(_instance: &InstanceConfig, variant: Option<&ServerType>) -> Capabilities {
+        Capabilities {
+            list: true,
+            exec: true,
+            mods: matches!(variant, Some(ServerType::Forge | ServerType::Paper)),
+            restart: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn readiness(_instance: &InstanceConfig) -> Readiness {
+        // `[12:34:56 INFO]: Done (12.345s)! For help, type "help"`
+        Readiness::Console(Regex::new(r"]: Done \(\d+(\.\d+)?s\)!").expect("valid regex"))
+    }
+
+    fn crash_artifacts(server_path: &Path, since: SystemTime) -> Vec<PathBuf> {
+        let report = newest_file(&server_path.join("crash-reports"), since, |name| {
+            name.starts_with("crash-") && name.ends_with(".txt")
+        });
+        // written by the jvm itself when it crashes.
+        let hs_err = newest_file(server_path, since, |name| {
+            name.starts_with("hs_err_pid") && name.ends_with(".log")
+        });
+
+        report.into_iter().chain(hs_err).collect()
+    }
+
+    fn progress(line: &str) -> Option<Progress> {
+        // skip the `[12:34:56 INFO]: ` or `[12:34:56] [main/INFO] [logger]: ` prefix.
+        let (_, msg) = line.split_once("]: ")?;
+
+        let progress = if msg.starts_with("ModLauncher running") || msg.starts_with("[bootstrap]") {
+            Progress::new(5, "launching")
+        } else if msg.starts_with("Forge mod loading") {
+            Progress::new(10, "loading mods")
+        } else if msg.starts_with("Starting minecraft server version") {
+            Progress::new(30, "starting server")
+        } else if msg.starts_with("Preparing level") {
+            Progress::new(45, "preparing level")
+        } else if msg.starts_with("Preparing start region") {
+            Progress::new(50, "preparing spawn area")
+        } else if let Some(percent) = msg.strip_prefix("Preparing spawn area: ") {
+            Progress::new(50 + parse_percent(percent)? / 2, "preparing spawn area")
+        } else {
+            return None;
+        };
+
+        Some(progress)
+    }
+
     async fn server_info(
         client: &Client,
-        server_path: &Path,
+        instance: &InstanceConfig,
         start_time: SystemTime,
         variant: ServerType,
     ) -> anyhow::Result<ServerInfo> {
+        let server_path = &instance.server_dir;
         match variant {
             ServerType::Forge => forge::info(server_path, start_time, client).await,
             ServerType::Paper => paper::info(server_path, start_time, client).await,
@@ -70,7 +180,8 @@ impl GameServer<ServerType> for Minecraft {
 }
 
 impl Variant for ServerType {
-    fn detect(server_path: &Path) -> Option<Self> {
+    fn detect(instance: &InstanceConfig) -> Option<Self> {
+        let server_path = &instance.server_dir;
         if server_path.join("libraries/net/minecraftforge").exists() {
             Some(Self::Forge)
         } else if server_path.join("libraries/com/velocitypowered").exists() {
@@ -82,3 +193,41 @@ impl Variant for ServerType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{GameServer, Minecraft, Readiness};
+    use crate::config::InstanceConfig;
+
+    #[test]
+    fn readiness() {
+        let Readiness::Console(regex) = Minecraft::readiness(&InstanceConfig::default()) else {
+            panic!("expected a console probe");
+        };
+
+        // paper
+        assert!(regex.is_match(r#"[12:34:56 INFO]: Done (5.123s)! For help, type "help""#));
+        // forge
+        assert!(regex.is_match(
+            r#"[12:34:56] [Server thread/INFO] [minecraft/DedicatedServer]: Done (42.5s)! For help, type "help""#
+        ));
+        assert!(!regex.is_match("[12:34:56 INFO]: <player> Done (5s)!"));
+    }
+
+    #[test]
+    fn progress() {
+        let progress = |line| Minecraft::progress(line).map(|p| (p.percent, p.stage));
+
+        assert_eq!(
+            progress("[12:34:56 INFO]: Preparing spawn area: 43%"),
+            Some((71, "preparing spawn area".to_string()))
+        );
+        assert_eq!(
+            progress(
+                "[12:34:56] [main/INFO] [net.minecraftforge.common.ForgeMod/FORGEMOD]: Forge mod loading, version 47.2.0, for MC 1.20.1"
+            ),
+            Some((10, "loading mods".to_string()))
+        );
+        assert_eq!(progress("[12:34:56 INFO]: <player> hello"), None);
+    }
+}
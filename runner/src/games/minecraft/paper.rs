@@ -4,11 +4,11 @@ use reqwest::Client;
 use serde::Deserialize;
 
 use super::meta;
-use crate::ServerInfo;
+use crate::{ServerInfo, games::GameArgs};
 use meta::{ModMeta, extract_jar};
 
-pub fn args(server_path: &Path) -> Result<Vec<String>, &'static str> {
-    super::vanilla::args_with_jar_name(server_path, "paper")
+pub fn args(server_path: &Path, game_args: &GameArgs) -> Result<Vec<String>, &'static str> {
+    super::vanilla::args_with_jar_name(server_path, game_args, "paper")
 }
 
 #[derive(Deserialize)]
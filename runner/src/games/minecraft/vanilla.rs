@@ -1,19 +1,26 @@
-use std::{env, ffi::OsString, fs::DirEntry, path::Path, time::SystemTime};
+use std::{ffi::OsString, fs::DirEntry, path::Path, time::SystemTime};
 
 use super::meta::get_version;
-use crate::{ServerInfo, games::ARG_SEP};
+use crate::{
+    ServerInfo,
+    games::{GameArgs, parse_or, property},
+};
 
-pub fn args(server_path: &Path) -> Result<Vec<String>, &'static str> {
-    args_with_jar_name(server_path, "server")
+pub fn args(server_path: &Path, game_args: &GameArgs) -> Result<Vec<String>, &'static str> {
+    args_with_jar_name(server_path, game_args, "server")
 }
 
-pub fn args_with_jar_name(server_path: &Path, jar_name: &str) -> Result<Vec<String>, &'static str> {
+pub fn args_with_jar_name(
+    server_path: &Path,
+    game_args: &GameArgs,
+    jar_name: &str,
+) -> Result<Vec<String>, &'static str> {
     let mut args = Vec::new();
 
     if server_path.join("user_jvm_args.txt").exists() {
         args.push("@user_jvm_args.txt".to_string());
-    } else if let Ok(jvm_args) = env::var("GAME_ARGS") {
-        args.extend(jvm_args.trim().split(ARG_SEP).map(ToString::to_string));
+    } else if !game_args.is_empty() {
+        args.extend(game_args.iter().map(ToString::to_string));
     } else {
         tracing::warn!("could not find `user_jvm_args.txt` file");
     }
@@ -57,31 +64,25 @@ fn find_jar(server_path: &Path, name: &str) -> Result<OsString, &'static str> {
     }
 }
 
-pub fn port(server_path: &Path) -> Option<u32> {
-    if server_path
-        .join("server.properties")
-        .try_exists()
-        .is_ok_and(|e| e)
-    {
-        let mut port = None;
-        for line in std::fs::read_to_string(server_path).ok()?.lines() {
-            let line = line.trim();
-            if line.starts_with("query.port=") {
-                port = Some(line.split('=').last()?.parse().ok()?)
-            }
-        }
-
-        port
-    } else {
-        None
-    }
+/// from `server.properties`, or `25565`.
+pub fn port(server_path: &Path) -> u32 {
+    let properties = std::fs::read_to_string(server_path.join("server.properties"));
+    parse_or(
+        "server-port",
+        properties
+            .as_deref()
+            .ok()
+            .and_then(|p| property(p, "server-port")),
+        25565,
+    )
 }
 
 pub async fn info(server_path: &Path, start_time: SystemTime) -> anyhow::Result<ServerInfo> {
     Ok(ServerInfo {
-        port: port(server_path).unwrap_or(25565),
+        port: port(server_path),
         start_time,
         version: get_version(&server_path.join("versions"), "vanilla").await?,
         mods: vec![],
+        startup_duration: None,
     })
 }
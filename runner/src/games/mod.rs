@@ -1,14 +1,28 @@
-use std::{fmt::Debug, path::Path, str::FromStr, sync::Arc, time::SystemTime};
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
 
+use common::{Capabilities, Progress};
+use regex::Regex;
 use reqwest::{Client, StatusCode};
 use serde::Serialize;
-use tokio::process::Child;
+use tokio::process::{Child, Command};
 use tracing::warn;
 #[cfg(windows)]
 use win32_version_info::VersionInfo;
 
-use crate::{AppState, ServerInfo};
+use crate::{
+    AppState, ServerInfo,
+    config::{Config, InstanceConfig},
+    restart::RestartPolicy,
+    stopping::StopPolicy,
+};
 
+mod custom;
+pub use custom::Custom;
 mod minecraft;
 pub use minecraft::Minecraft;
 mod satisfactory;
@@ -16,14 +30,43 @@ pub use satisfactory::Satisfactory;
 mod terraria;
 pub use terraria::Terraria;
 
-pub(super) const ARG_SEP: char = '\\';
+mod args;
+pub use args::GameArgs;
+use args::{arg_or, parse_or, property};
+
+/// every game the runner supports, keyed by [`Game::name`].
+static GAMES: [&dyn Game; 4] = [&Minecraft, &Terraria, &Satisfactory, &Custom];
+
+/// the game called `name`, ignoring case.
+pub fn get(name: &str) -> Option<&'static dyn Game> {
+    GAMES
+        .iter()
+        .copied()
+        .find(|game| game.name().eq_ignore_ascii_case(name))
+}
+
+pub fn all() -> impl Iterator<Item = &'static dyn Game> {
+    GAMES.iter().copied()
+}
+
+/// the names of every game, eg. for a config error.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    GAMES.iter().map(|game| game.name())
+}
 
 pub(super) type RunResult = Result<tokio::io::Result<Child>, (StatusCode, &'static str)>;
 
-pub(super) trait GameServer<V: Variant + Debug + Send + Clone + 'static> {
-    /// Spawns the game server and sets the [`AppState`]'s `server_info` asynchronously.
-    fn run(state: Arc<AppState>, server_path: &Path) -> RunResult {
-        let Some(variant) = V::detect(server_path) else {
+/// A game server the runner can run, implemented through [`Game`].
+pub(super) trait GameServer {
+    type Variant: Variant + Debug + Send + Clone + 'static;
+
+    /// What `server_type` is set to for this game.
+    const NAME: &'static str;
+
+    /// Spawns the instance's game server and sets the [`AppState`]'s `server_info` asynchronously.
+    fn run(state: Arc<AppState>) -> RunResult {
+        let instance = state.config();
+        let Some(variant) = Self::Variant::detect(&instance) else {
             tracing::warn!("no server detected at the configured path");
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -31,13 +74,35 @@ pub(super) trait GameServer<V: Variant + Debug + Send + Clone + 'static> {
             ));
         };
         tracing::debug!("detected server type {variant:?}");
+        state
+            .server_variant
+            .lock()
+            .expect("not poisoned")
+            .replace(format!("{variant:?}"));
 
-        let owned_path = server_path.to_owned();
-        let variant_1 = variant.clone();
+        Self::fetch_info(state, instance.clone(), SystemTime::now(), variant.clone());
+        Self::spawn(&instance, variant)
+    }
+    /// Re-detects the variant of a game server that was started by a previous runner and sets its `server_info` asynchronously.
+    ///
+    /// Returns the variant, if it could be detected.
+    fn adopt(state: Arc<AppState>, start_time: SystemTime) -> Option<String> {
+        let instance = state.config();
+        let variant = Self::Variant::detect(&instance)?;
+        let name = format!("{variant:?}");
+        Self::fetch_info(state, instance, start_time, variant);
+        Some(name)
+    }
+    /// Sets the [`AppState`]'s `server_info` asynchronously.
+    fn fetch_info(
+        state: Arc<AppState>,
+        instance: Arc<InstanceConfig>,
+        start_time: SystemTime,
+        variant: Self::Variant,
+    ) {
         tokio::spawn(async move {
-            let start_time = SystemTime::now();
             tracing::info!("detecting server info");
-            match Self::server_info(&state.client, &owned_path, start_time, variant_1).await {
+            match Self::server_info(&state.client, &instance, start_time, variant).await {
                 Ok(info) => {
                     tracing::info!("found server info ({:?})", start_time.elapsed());
                     state.server_info.write().await.replace(info);
@@ -45,24 +110,178 @@ pub(super) trait GameServer<V: Variant + Debug + Send + Clone + 'static> {
                 Err(err) => warn!("could not find server info: {err}"),
             }
         });
-        Self::spawn(server_path, variant)
     }
     /// Spawns the game server.
-    fn spawn(server_path: &Path, variant: V) -> RunResult;
+    fn spawn(instance: &InstanceConfig, variant: Self::Variant) -> RunResult;
+    /// Whether the process we spawn is a wrapper around the actual game server.
+    fn wrapped() -> bool {
+        false
+    }
+    /// Written to the game server's stdin once it has spawned, eg. to get past a prompt.
+    fn startup_input() -> Option<&'static str> {
+        None
+    }
     /// Gracefully stops the game server. Should not block.
     fn stop(state: Arc<AppState>) -> anyhow::Result<()>;
+    /// Announces `msg` to everyone in-game.
+    fn announce(_state: &AppState, _msg: &str) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("announcing is not supported"))
+    }
+    /// The console command that lists the online players, if there is one.
+    fn list_command(_instance: &InstanceConfig) -> Option<String> {
+        Some("/list".to_string())
+    }
+    /// Named regexes of console lines that `[filters]` rules can use, which are passed unredacted by default.
+    fn line_patterns() -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
+    /// How to tell that the game server is joinable.
+    fn readiness(instance: &InstanceConfig) -> Readiness;
+    /// Parses the startup progress from a console line, if the line shows any.
+    fn progress(_line: &str) -> Option<Progress> {
+        None
+    }
+    /// Finds the crash logs the game server wrote after `since`, once it has exited abnormally.
+    fn crash_artifacts(_server_path: &Path, _since: SystemTime) -> Vec<PathBuf> {
+        Vec::new()
+    }
+    /// How long to wait for the game server to stop, before the `[stop]` config.
+    fn stop_policy() -> StopPolicy {
+        StopPolicy::default()
+    }
+    /// What to do when the game server crashes, before the `[restart]` config.
+    ///
+    /// No game overrides this yet, so `[restart]` is how it is tuned per server.
+    fn restart_policy() -> RestartPolicy {
+        RestartPolicy::default()
+    }
+    /// What the game server supports, for `variant` if it could be detected.
+    fn capabilities(instance: &InstanceConfig, _variant: Option<&Self::Variant>) -> Capabilities {
+        Capabilities {
+            list: Self::list_command(instance).is_some(),
+            exec: true,
+            restart: true,
+            ..Capabilities::default()
+        }
+    }
+    /// Problems with `config` specific to this game, for each `instance` of it.
+    fn config_problems(_config: &Config, _instance: &InstanceConfig) -> Vec<String> {
+        Vec::new()
+    }
+    /// Logs what is useful to know about the setup at startup.
+    fn log_setup(_instance: &InstanceConfig) {}
     /// Gets the server's info.
     fn server_info(
         client: &Client,
-        server_path: &Path,
+        instance: &InstanceConfig,
         start_time: SystemTime,
-        variant: V,
+        variant: Self::Variant,
     ) -> impl Future<Output = anyhow::Result<ServerInfo>> + Send;
 }
 
+/// An object-safe [`GameServer`], so an instance's game can be looked up by name with [`get`].
+pub trait Game: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn run(&self, state: Arc<AppState>) -> RunResult;
+    fn adopt(&self, state: Arc<AppState>, start_time: SystemTime) -> Option<String>;
+    fn wrapped(&self) -> bool;
+    fn startup_input(&self) -> Option<&'static str>;
+    fn stop(&self, state: Arc<AppState>) -> anyhow::Result<()>;
+    fn announce(&self, state: &AppState, msg: &str) -> anyhow::Result<()>;
+    fn list_command(&self, instance: &InstanceConfig) -> Option<String>;
+    fn line_patterns(&self) -> &'static [(&'static str, &'static str)];
+    fn readiness(&self, instance: &InstanceConfig) -> Readiness;
+    fn progress(&self, line: &str) -> Option<Progress>;
+    fn crash_artifacts(&self, server_path: &Path, since: SystemTime) -> Vec<PathBuf>;
+    fn stop_policy(&self) -> StopPolicy;
+    fn restart_policy(&self) -> RestartPolicy;
+    /// Detects the instance's variant to tell what it supports.
+    fn capabilities(&self, instance: &InstanceConfig) -> Capabilities;
+    fn config_problems(&self, config: &Config, instance: &InstanceConfig) -> Vec<String>;
+    fn log_setup(&self, instance: &InstanceConfig);
+}
+
+impl<G: GameServer + Send + Sync> Game for G {
+    fn name(&self) -> &'static str {
+        G::NAME
+    }
+    fn run(&self, state: Arc<AppState>) -> RunResult {
+        G::run(state)
+    }
+    fn adopt(&self, state: Arc<AppState>, start_time: SystemTime) -> Option<String> {
+        G::adopt(state, start_time)
+    }
+    fn wrapped(&self) -> bool {
+        G::wrapped()
+    }
+    fn startup_input(&self) -> Option<&'static str> {
+        G::startup_input()
+    }
+    fn stop(&self, state: Arc<AppState>) -> anyhow::Result<()> {
+        G::stop(state)
+    }
+    fn announce(&self, state: &AppState, msg: &str) -> anyhow::Result<()> {
+        G::announce(state, msg)
+    }
+    fn list_command(&self, instance: &InstanceConfig) -> Option<String> {
+        G::list_command(instance)
+    }
+    fn line_patterns(&self) -> &'static [(&'static str, &'static str)] {
+        G::line_patterns()
+    }
+    fn readiness(&self, instance: &InstanceConfig) -> Readiness {
+        G::readiness(instance)
+    }
+    fn progress(&self, line: &str) -> Option<Progress> {
+        G::progress(line)
+    }
+    fn crash_artifacts(&self, server_path: &Path, since: SystemTime) -> Vec<PathBuf> {
+        G::crash_artifacts(server_path, since)
+    }
+    fn stop_policy(&self) -> StopPolicy {
+        G::stop_policy()
+    }
+    fn restart_policy(&self) -> RestartPolicy {
+        G::restart_policy()
+    }
+    fn capabilities(&self, instance: &InstanceConfig) -> Capabilities {
+        G::capabilities(instance, G::Variant::detect(instance).as_ref())
+    }
+    fn config_problems(&self, config: &Config, instance: &InstanceConfig) -> Vec<String> {
+        G::config_problems(config, instance)
+    }
+    fn log_setup(&self, instance: &InstanceConfig) {
+        G::log_setup(instance)
+    }
+}
+
+/// Puts the game server in its own process group on unix.
+///
+/// This lets [`runner::kill_tree`] reach every descendant, and keeps a ctrl+c in the runner's terminal
+/// from reaching the server before we can stop it gracefully.
+pub(super) fn own_process_group(cmd: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd
+}
+
+/// How to tell that a game server is joinable, after it has spawned.
+#[derive(Debug)]
+pub enum Readiness {
+    /// A line of the console matches.
+    Console(Regex),
+    /// The port accepts TCP connections.
+    Port(u16),
+}
+
+/// Parses a percentage like `43%`.
+fn parse_percent(percent: &str) -> Option<u8> {
+    percent.trim().strip_suffix('%')?.trim().parse().ok()
+}
+
 /// A game server's variant.
 pub(super) trait Variant: Sized {
-    fn detect(server_path: &Path) -> Option<Self>;
+    fn detect(instance: &InstanceConfig) -> Option<Self>;
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -94,30 +313,17 @@ fn version_info(file_path: &Path) -> anyhow::Result<VersionInfo> {
     Ok(VersionInfo::from_file(file_path)?)
 }
 
-/// Gets `arg` from `file` first, if it's Some, else get from `GAME_ARGS`, else return the `default`.
-///
-/// `arg` should include all characters before the arg value, including the separator (eg. ` `, `=`)
-fn get_arg_or<T: FromStr + std::fmt::Display>(arg: &str, file: Option<&Path>, default: T) -> T {
-    assert!(!arg.is_empty());
-    let sep = arg.chars().last().unwrap();
-
-    let args = if let Some(file) = file
-        && file.try_exists().is_ok_and(|e| e)
-    {
-        Some(std::fs::read_to_string(file).unwrap())
-    } else if let Ok(args) = std::env::var("GAME_ARGS") {
-        Some(args)
-    } else {
-        None
-    };
+/// mirrors the fields of `win32_version_info::VersionInfo` that we use.
+#[cfg(not(windows))]
+#[allow(dead_code)]
+struct VersionInfo {
+    file_version: String,
+    product_version: String,
+}
 
-    if let Some(args) = args
-        && let Some(arg) = args.split(ARG_SEP).find(|a| a.starts_with(arg))
-        && let Some(value) = arg.split(sep).last().and_then(|p| p.parse().ok())
-    {
-        value
-    } else {
-        tracing::warn!("using default {arg}{default}");
-        default
-    }
+#[cfg(not(windows))]
+fn version_info(file_path: &Path) -> anyhow::Result<VersionInfo> {
+    Err(anyhow::anyhow!(
+        "cannot read version info of {file_path:?}, only supported on windows"
+    ))
 }
@@ -1,19 +1,19 @@
 use std::{
-    env,
-    path::Path,
     process::Stdio,
     sync::{Arc, atomic::Ordering},
     time::SystemTime,
 };
 
 use anyhow::{Context, anyhow};
+use common::Capabilities;
 use reqwest::Client;
 use tokio::process::Command;
 
-use super::{GameServer, RunResult, Variant};
+use super::{GameServer, Readiness, RunResult, Variant, own_process_group};
 use crate::{
     AppState, ServerInfo,
-    games::{ARG_SEP, version_info},
+    config::InstanceConfig,
+    games::{GameArgs, arg_or, version_info},
 };
 
 pub struct Satisfactory;
@@ -23,8 +23,12 @@ pub enum ServerType {
     BaseGame,
 }
 
-impl GameServer<ServerType> for Satisfactory {
-    fn spawn(server_path: &Path, _variant: ServerType) -> RunResult {
+impl GameServer for Satisfactory {
+    type Variant = ServerType;
+    const NAME: &'static str = "satisfactory";
+
+    fn spawn(instance: &InstanceConfig, _variant: ServerType) -> RunResult {
+        let server_path = &instance.server_dir;
         let exe = if cfg!(windows) {
             server_path.join("Engine/Binaries/Win64/FactoryServer-Win64-Shipping-Cmd.exe")
         } else {
@@ -33,26 +37,26 @@ impl GameServer<ServerType> for Satisfactory {
 
         let mut cmd = Command::new(exe);
 
-        if cfg!(windows) {
+        #[cfg(windows)]
+        {
             use windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP;
 
             cmd.args(["FactoryGame", "-unattended"]);
             cmd.current_dir(server_path.join("Engine/Binaries/Win64/"));
             // to prevent sending ctrlc to us (the parent process)
             cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
-        } else {
-            cmd.arg("FactoryServer.sh").current_dir(server_path);
         }
 
-        if let Ok(user_args) = env::var("GAME_ARGS") {
-            cmd.args(user_args.trim().split(ARG_SEP).map(ToString::to_string));
-        }
+        #[cfg(not(windows))]
+        cmd.arg("FactoryServer.sh").current_dir(server_path);
 
-        let child = cmd
-            .stdout(Stdio::piped())
+        cmd.args(instance.game_args.iter());
+
+        cmd.stdout(Stdio::piped())
             .stdin(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn();
+            .stderr(Stdio::piped());
+
+        let child = own_process_group(&mut cmd).spawn();
 
         Ok(child)
     }
@@ -64,64 +68,56 @@ impl GameServer<ServerType> for Satisfactory {
             return Err(anyhow!("tried to stop but pid was 0"));
         }
 
-        #[cfg(windows)]
-        {
-            use windows_sys::Win32::System::Console::{CTRL_BREAK_EVENT, GenerateConsoleCtrlEvent};
+        runner::interrupt(pid).map_err(|err| anyhow!("failed to interrupt pid {pid}: {err}"))
+    }
 
-            let res = unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) };
-            if res == 0 {
-                let err = std::io::Error::last_os_error();
-                return Err(anyhow!("failed to send ctrlc to pid {pid}: {err}"));
-            }
-        }
+    fn list_command(_instance: &InstanceConfig) -> Option<String> {
+        None
+    }
 
-        #[cfg(unix)]
-        {
-            let res = unsafe { libc::kill(pid, libc::SIGINT) };
-            if res == -1 {
-                let error = std::io::Error::last_os_error();
-                return Err(anyhow!("failed to send SIGINT to pid {pid}: {err}"));
-            }
+    // it has no console commands, only its https api.
+    fn capabilities(_instance: &InstanceConfig, _variant: Option<&ServerType>) -> Capabilities {
+        Capabilities {
+            restart: true,
+            ..Capabilities::default()
         }
+    }
 
-        Ok(())
+    fn readiness(instance: &InstanceConfig) -> Readiness {
+        // the server api listens on the game port over TCP.
+        Readiness::Port(port(&instance.game_args))
     }
 
     async fn server_info(
         _client: &Client,
-        server_path: &Path,
+        instance: &InstanceConfig,
         start_time: SystemTime,
         _variant: ServerType,
     ) -> anyhow::Result<crate::ServerInfo> {
-        let v_info = version_info(&server_path.join("FactoryServer.exe"))
+        let v_info = version_info(&instance.server_dir.join("FactoryServer.exe"))
             .context("getting version from file")?;
         let v_info: Vec<&str> = v_info
             .product_version // ++FactoryGame+rel-main-1.2.0-CL-495413
             .split('-')
             .skip(1)
             .collect();
-        let port = {
-            if let Ok(args) = env::var("GAME_ARGS")
-                && let Some(arg) = args.split(ARG_SEP).find(|a| a.starts_with("-Port="))
-                && let Some(port) = arg.split('=').last().and_then(|p| p.parse().ok())
-            {
-                port
-            } else {
-                7777
-            }
-        };
-
         Ok(ServerInfo {
-            port,
+            port: port(&instance.game_args).into(),
             version: format!("v{} ({}, build {})", v_info[1], v_info[0], v_info[3]),
             start_time,
             mods: vec![],
+            startup_duration: None,
         })
     }
 }
 
+fn port(args: &GameArgs) -> u16 {
+    arg_or(args, "-Port", 7777)
+}
+
 impl Variant for ServerType {
-    fn detect(server_path: &Path) -> Option<Self> {
+    fn detect(instance: &InstanceConfig) -> Option<Self> {
+        let server_path = &instance.server_dir;
         if (cfg!(windows) && server_path.join("FactoryServer.exe").exists())
             || (cfg!(unix) && server_path.join("./FactoryServer.sh").exists())
         {
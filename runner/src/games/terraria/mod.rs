@@ -1,13 +1,21 @@
 use anyhow::anyhow;
+use common::{Capabilities, Progress};
+use regex::Regex;
 use std::{
-    env::{self, current_dir},
     path::{Path, PathBuf},
     sync::Arc,
     time::SystemTime,
 };
 
-use super::{GameServer, RunResult, Variant};
-use crate::AppState;
+use super::{
+    GameServer, Readiness, RunResult, Variant, own_process_group, parse_percent, property,
+};
+use crate::{
+    AppState,
+    config::{Config, InstanceConfig},
+    crash::files_since,
+    stopping::StopPolicy,
+};
 
 mod tmodloader;
 mod vanilla;
@@ -21,14 +29,30 @@ pub enum ServerType {
     TModLoader(PathBuf),
 }
 
-impl GameServer<ServerType> for Terraria {
-    fn spawn(server_path: &Path, variant: ServerType) -> RunResult {
-        let mut cmd = match variant {
-            ServerType::TModLoader(_) => tmodloader::command(server_path),
-            ServerType::Vanilla => vanilla::command(server_path),
+impl GameServer for Terraria {
+    type Variant = ServerType;
+    const NAME: &'static str = "terraria";
+
+    fn spawn(instance: &InstanceConfig, variant: ServerType) -> RunResult {
+        let cmd = match variant {
+            ServerType::TModLoader(_) => tmodloader::command(instance),
+            ServerType::Vanilla => vanilla::command(instance),
+        };
+        let mut cmd = match cmd {
+            Ok(cmd) => cmd,
+            Err(err) => return Ok(Err(err)),
         };
 
-        Ok(cmd.spawn())
+        Ok(own_process_group(&mut cmd).spawn())
+    }
+
+    // on windows, we spawn a launcher.
+    fn wrapped() -> bool {
+        cfg!(windows)
+    }
+
+    fn startup_input() -> Option<&'static str> {
+        Some("\n")
     }
 
     fn stop(state: Arc<AppState>) -> anyhow::Result<()> {
@@ -39,44 +63,110 @@ impl GameServer<ServerType> for Terraria {
         }
     }
 
+    fn announce(state: &AppState, msg: &str) -> anyhow::Result<()> {
+        if let Err(err) = state.server_stdin.send(format!("say {msg}")) {
+            Err(anyhow!("failed to send `say`: {err}"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn stop_policy() -> StopPolicy {
+        StopPolicy {
+            // printed once the world has been saved, right before exiting.
+            saved: Some(Regex::new(r"Backing up world file").expect("valid regex")),
+            ..StopPolicy::default()
+        }
+    }
+
+    fn readiness(_instance: &InstanceConfig) -> Readiness {
+        // both vanilla and tmodloader print this once the world is loaded.
+        Readiness::Console(Regex::new(r"^\s*Server started").expect("valid regex"))
+    }
+
+    fn progress(line: &str) -> Option<Progress> {
+        // progress is redrawn with carriage returns, so only look at the newest.
+        let line = line.rsplit('\r').find(|l| !l.trim().is_empty())?.trim();
+
+        let progress = if line.starts_with("Finding Mods") {
+            Progress::new(5, "finding mods")
+        } else if let Some(name) = ["Loading mod: ", "Sandboxing: ", "Initializing: "]
+            .iter()
+            .find_map(|prefix| line.strip_prefix(prefix))
+        {
+            Progress::new(15, format!("loading mods ({name})"))
+        } else if line.starts_with("Setting up") || line.starts_with("Adding Recipes") {
+            Progress::new(30, "setting up mods")
+        } else if line.starts_with("Resetting game objects") {
+            Progress::new(35, "resetting game objects")
+        } else if let Some(percent) = line.strip_prefix("Loading world data: ") {
+            Progress::new(40 + parse_percent(percent)? * 2 / 5, "loading world")
+        } else if let Some(percent) = line.strip_prefix("Settling liquids: ") {
+            Progress::new(80 + parse_percent(percent)? / 5, "settling liquids")
+        } else {
+            return None;
+        };
+
+        Some(progress)
+    }
+
+    fn crash_artifacts(server_path: &Path, since: SystemTime) -> Vec<PathBuf> {
+        // tmodloader logs everything, including the exception, to `tModLoader-Logs/server.log`.
+        files_since(&server_path.join("tModLoader-Logs"), since, |name| {
+            name.ends_with(".log")
+        })
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect()
+    }
+
+    fn capabilities(_instance: &InstanceConfig, variant: Option<&ServerType>) -> Capabilities {
+        Capabilities {
+            list: true,
+            exec: true,
+            mods: matches!(variant, Some(ServerType::TModLoader(_))),
+            restart: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn config_problems(config: &Config, _instance: &InstanceConfig) -> Vec<String> {
+        if config.steam_apikey.is_none() {
+            vec!["`steam_apikey` (`STEAM_APIKEY`) is required for terraria".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn log_setup(instance: &InstanceConfig) {
+        tracing::info!("terraria server set at {:?}", instance.server_dir);
+    }
+
     async fn server_info(
         client: &reqwest::Client,
-        server_path: &Path,
+        instance: &InstanceConfig,
         start_time: SystemTime,
         variant: ServerType,
     ) -> anyhow::Result<crate::ServerInfo> {
         match variant {
             ServerType::TModLoader(world) => {
-                tmodloader::info(client, server_path, world, start_time).await
+                tmodloader::info(client, instance, world, start_time).await
             }
-            ServerType::Vanilla => vanilla::info(server_path, start_time),
+            ServerType::Vanilla => vanilla::info(instance, start_time),
         }
     }
 }
 
 impl Variant for ServerType {
-    fn detect(server_path: &Path) -> Option<Self> {
+    fn detect(instance: &InstanceConfig) -> Option<Self> {
+        let server_path = &instance.server_dir;
         if server_path.join("TerrariaServer.exe").exists() {
             Some(Self::Vanilla)
         } else if server_path.join("tModLoader.dll").exists() {
-            let Some(config) = find_config() else {
-                tracing::error!("could not determine world location from config or args");
-                return None;
-            };
-
-            let mut world = None;
-
-            for line in config.lines() {
-                if line.starts_with("world=") {
-                    world = Some(line.split('=').next_back()?);
-                    break;
-                }
-            }
-
-            if let Some(world) = world {
-                Some(Self::TModLoader(PathBuf::from(world)))
+            if let Some(world) = find_world(instance) {
+                Some(Self::TModLoader(world))
             } else {
-                tracing::error!("world was not set in config");
+                tracing::error!("world was not set in `game_args` or `terrariaConfig.txt`");
                 None
             }
         } else {
@@ -85,9 +175,96 @@ impl Variant for ServerType {
     }
 }
 
-fn find_config() -> Option<String> {
-    let file_config = std::fs::read_to_string(current_dir().ok()?.join("terrariaConfig.txt")).ok();
-    let user_config = env::var("GAME_ARGS").ok();
+/// the instance's `terrariaConfig.txt`, if it has one, which takes precedence over its `game_args`.
+fn config_file(instance: &InstanceConfig) -> Option<PathBuf> {
+    let path = instance.server_dir.join("terrariaConfig.txt");
+    path.try_exists().is_ok_and(|e| e).then_some(path)
+}
+
+/// from `game_args` first, since they take precedence for tmodloader.
+fn find_world(instance: &InstanceConfig) -> Option<PathBuf> {
+    if let Some(world) = instance.game_args.value("-world") {
+        return Some(PathBuf::from(world));
+    }
+
+    let config_file = std::fs::read_to_string(config_file(instance)?).ok()?;
+    property(&config_file, "world").map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, process};
+
+    use super::{GameServer, Terraria, find_world, vanilla};
+    use crate::config::InstanceConfig;
 
-    file_config.or(user_config)
+    #[test]
+    fn config_file() {
+        let dir = std::env::temp_dir().join(format!("runner-terraria-{}", process::id()));
+        let (configured, bare) = (dir.join("configured"), dir.join("bare"));
+        fs::create_dir_all(&configured).unwrap();
+        fs::create_dir_all(&bare).unwrap();
+        fs::write(
+            configured.join("terrariaConfig.txt"),
+            "world=worlds/a.wld\nport=7778\n",
+        )
+        .unwrap();
+
+        // each instance reads its own server directory's.
+        let configured = InstanceConfig {
+            server_dir: configured,
+            ..InstanceConfig::default()
+        };
+        let bare = InstanceConfig {
+            server_dir: bare,
+            game_args: "-port 7779".parse().unwrap(),
+            ..InstanceConfig::default()
+        };
+        assert_eq!(vanilla::port(&configured), 7778);
+        assert_eq!(find_world(&configured), Some(PathBuf::from("worlds/a.wld")));
+        assert_eq!(vanilla::port(&bare), 7779);
+        assert_eq!(find_world(&bare), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn progress() {
+        let progress = |line| Terraria::progress(line).map(|p| (p.percent, p.stage));
+
+        // tmodloader loading its mods.
+        assert_eq!(
+            progress("Finding Mods..."),
+            Some((5, "finding mods".to_string()))
+        );
+        assert_eq!(
+            progress("Sandboxing: CalamityMod"),
+            Some((15, "loading mods (CalamityMod)".to_string()))
+        );
+        assert_eq!(
+            progress("Initializing: ModLoader"),
+            Some((15, "loading mods (ModLoader)".to_string()))
+        );
+        assert_eq!(progress("Instantiating Mods..."), None);
+        assert_eq!(
+            progress("Adding Recipes..."),
+            Some((30, "setting up mods".to_string()))
+        );
+
+        // then loading the world like vanilla, redrawn with carriage returns.
+        assert_eq!(
+            progress("Resetting game objects 33%"),
+            Some((35, "resetting game objects".to_string()))
+        );
+        assert_eq!(
+            progress("Loading world data: 1%\rLoading world data: 50%\r"),
+            Some((60, "loading world".to_string()))
+        );
+        assert_eq!(
+            progress("Settling liquids: 100%"),
+            Some((100, "settling liquids".to_string()))
+        );
+        assert_eq!(progress("Loading world data: "), None);
+        assert_eq!(progress("Server started"), None);
+    }
 }
@@ -1,18 +1,24 @@
-use std::{collections::HashMap, env, fs::File, io::BufReader, path::Path, process::Stdio};
-#[cfg(windows)]
-use std::{path::PathBuf, time::SystemTime};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader},
+    path::{self, Path, PathBuf},
+    process::Stdio,
+    time::SystemTime,
+};
 
 use crate::{
     ServerInfo,
-    games::{ARG_SEP, Mod, terraria::workshop::search},
+    config::InstanceConfig,
+    games::{Mod, terraria::workshop::search},
 };
 use flate2::bufread::GzDecoder;
-#[cfg(windows)]
 use reqwest::Client;
 use serde::Deserialize;
 use tokio::process::Command;
 
-pub fn command(server_path: &Path) -> Command {
+pub fn command(instance: &InstanceConfig) -> io::Result<Command> {
+    let server_path = &instance.server_dir;
     let exe = if cfg!(windows) {
         server_path.join("LaunchUtils/busybox64.exe")
     } else {
@@ -29,16 +35,12 @@ pub fn command(server_path: &Path) -> Command {
 
     cmd.arg(server_path.join("start-tModLoaderServer.sh"));
 
-    if let Ok(user_args) = env::var("GAME_ARGS") {
-        cmd.args(user_args.trim().split(ARG_SEP).map(ToString::to_string));
-    } else {
-        let config_file = env::current_dir()
-            .expect("should have permission and exist")
-            .join("terrariaConfig.txt");
-        if config_file.try_exists().is_ok_and(|e| e) {
-            cmd.arg("-config");
-            cmd.arg(config_file);
-        }
+    if !instance.game_args.is_empty() {
+        cmd.args(instance.game_args.iter());
+    } else if let Some(config_file) = super::config_file(instance) {
+        // the server runs in `server_dir`, which may be relative to ours.
+        cmd.arg("-config");
+        cmd.arg(path::absolute(config_file)?);
     }
 
     cmd.stdout(Stdio::piped())
@@ -46,32 +48,32 @@ pub fn command(server_path: &Path) -> Command {
         .stderr(Stdio::piped())
         .current_dir(server_path);
 
-    cmd
+    Ok(cmd)
 }
 
-#[cfg(windows)]
 pub async fn info(
     client: &Client,
-    server_path: &Path,
+    instance: &InstanceConfig,
     mut world_path: PathBuf,
     start_time: SystemTime,
 ) -> anyhow::Result<ServerInfo> {
     use crate::games::version_info;
     use anyhow::Context;
 
-    let version = version_info(&server_path.join("tModLoader.dll"))
+    let version = version_info(&instance.server_dir.join("tModLoader.dll"))
         .context("finding version from file")?
         .file_version;
 
     world_path.set_extension("twld");
 
-    let port = super::vanilla::port();
+    let port = super::vanilla::port(instance);
 
     Ok(ServerInfo {
         port,
         start_time,
         version,
         mods: mods_from_twld(client, &world_path).await?,
+        startup_duration: None,
     })
 }
 
@@ -102,17 +104,20 @@ async fn mods_from_twld(client: &Client, path: &Path) -> anyhow::Result<Vec<Mod>
             continue;
         }
 
-        let r#mod = match search(client, &name).await {
-            Ok(item) => Mod::Resolved {
+        let resolved = search(client, &name).await.and_then(|item| {
+            Ok(Mod::Resolved {
                 name: name.clone(),
-                author: item.author().to_string(),
-                version,
+                author: item.author()?.to_string(),
+                version: version.clone(),
                 dependency: name.contains("Lib"),
                 // https://docs.tmodloader.net/docs/1.4-stable/namespace_terraria_1_1_mod_loader.html#a1c82c6b1930a8ee5c45efb091a036b06
-                required: ["Both", "Client"].contains(&item.modside()),
+                required: ["Both", "Client"].contains(&item.modside()?),
                 link: item.url(),
                 icon_url: item.preview_url,
-            },
+            })
+        });
+        let r#mod = match resolved {
+            Ok(r#mod) => r#mod,
             Err(err) => {
                 tracing::warn!("could not resolve mod {name}: {err}");
                 Mod::Unresolved {
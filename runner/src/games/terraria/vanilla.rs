@@ -1,27 +1,24 @@
-use std::{env, process::Stdio};
-#[cfg(windows)]
-use std::{path::Path, time::SystemTime};
+use std::{io, path, process::Stdio, time::SystemTime};
 
-#[cfg(windows)]
 use anyhow::Context;
 use tokio::process::Command;
 
-use crate::games::ARG_SEP;
-#[cfg(windows)]
-use crate::{ServerInfo, games::get_arg_or};
+use crate::{
+    ServerInfo,
+    config::InstanceConfig,
+    games::{parse_or, property},
+};
 
-pub fn command(server_path: &Path) -> Command {
+pub fn command(instance: &InstanceConfig) -> io::Result<Command> {
+    let server_path = &instance.server_dir;
     let mut cmd = Command::new(server_path.join("TerrariaServer.exe"));
 
-    let config_file = env::current_dir()
-        .expect("should have permission and exist")
-        .join("terrariaConfig.txt");
-
-    if config_file.try_exists().is_ok_and(|e| e) {
+    if let Some(config_file) = super::config_file(instance) {
+        // the server runs in `server_dir`, which may be relative to ours.
         cmd.arg("-config");
-        cmd.arg(config_file);
-    } else if let Ok(user_args) = env::var("GAME_ARGS") {
-        cmd.args(user_args.trim().split(ARG_SEP).map(ToString::to_string));
+        cmd.arg(path::absolute(config_file)?);
+    } else {
+        cmd.args(instance.game_args.iter());
     }
 
     cmd.stdout(Stdio::piped())
@@ -29,31 +26,35 @@ pub fn command(server_path: &Path) -> Command {
         .stderr(Stdio::piped())
         .current_dir(server_path);
 
-    cmd
+    Ok(cmd)
 }
 
-pub fn port() -> u32 {
-    get_arg_or(
-        "-port ",
-        Some(&env::current_dir().unwrap().join("terrariaConfig.txt")),
-        7777,
-    )
+/// from `terrariaConfig.txt` if it exists, else from `game_args`.
+pub fn port(instance: &InstanceConfig) -> u32 {
+    let config_file =
+        super::config_file(instance).and_then(|path| std::fs::read_to_string(path).ok());
+
+    if let Some(config_file) = config_file {
+        parse_or("port", property(&config_file, "port"), 7777)
+    } else {
+        parse_or("-port", instance.game_args.value("-port"), 7777)
+    }
 }
 
-#[cfg(windows)]
-pub fn info(server_path: &Path, start_time: SystemTime) -> anyhow::Result<ServerInfo> {
+pub fn info(instance: &InstanceConfig, start_time: SystemTime) -> anyhow::Result<ServerInfo> {
     use crate::games::version_info;
 
-    let version = version_info(&server_path.join("TerrariaServer.exe"))
+    let version = version_info(&instance.server_dir.join("TerrariaServer.exe"))
         .context("finding version from file")?
         .file_version;
 
-    let port = port();
+    let port = port(instance);
 
     Ok(ServerInfo {
         port,
         version,
         start_time,
         mods: vec![],
+        startup_duration: None,
     })
 }
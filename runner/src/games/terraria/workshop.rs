@@ -1,11 +1,8 @@
-use std::{env, sync::LazyLock};
-
 use anyhow::anyhow;
 use reqwest::Client;
 use serde::Deserialize;
 
-static API_KEY: LazyLock<String> =
-    LazyLock::new(|| env::var("STEAM_APIKEY").expect("should be set"));
+use crate::config::config;
 
 #[derive(Debug, Deserialize)]
 struct Response {
@@ -33,23 +30,24 @@ impl SearchItem {
         )
     }
 
-    pub fn name(&self) -> &str {
-        self.tag_value("name").expect("required")
+    pub fn name(&self) -> anyhow::Result<&str> {
+        self.tag_value("name")
     }
 
-    pub fn author(&self) -> &str {
-        self.tag_value("Author").expect("required")
+    pub fn author(&self) -> anyhow::Result<&str> {
+        self.tag_value("Author")
     }
 
-    pub fn modside(&self) -> &str {
-        self.tag_value("modside").expect("required")
+    pub fn modside(&self) -> anyhow::Result<&str> {
+        self.tag_value("modside")
     }
 
-    fn tag_value(&self, key: &str) -> Option<&str> {
+    fn tag_value(&self, key: &str) -> anyhow::Result<&str> {
         self.kvtags
             .iter()
             .find(|t| t.key == key)
             .map(|t| t.value.as_ref())
+            .ok_or_else(|| anyhow!("workshop item {} has no `{key}` tag", self.file_id))
     }
 }
 
@@ -63,8 +61,12 @@ pub struct ItemTag {
 pub async fn search(client: &Client, name: &str) -> anyhow::Result<SearchItem> {
     const URL: &str = "https://api.steampowered.com/IPublishedFileService/QueryFiles/v1/";
 
+    let api_key = config()
+        .steam_apikey
+        .clone()
+        .ok_or(anyhow!("no steam api key configured"))?;
     let query = &[
-        ("key", API_KEY.as_str()),
+        ("key", api_key.as_str()),
         ("query_type", "0"), // https://partner.steamgames.com/doc/webapi/IPublishedFileService#EPublishedFileQueryType
         ("page", "1"),
         ("numperpage", "3"),
@@ -82,7 +84,7 @@ pub async fn search(client: &Client, name: &str) -> anyhow::Result<SearchItem> {
         .response
         .publishedfiledetails
         .into_iter()
-        .find(|i| i.name() == name)
+        .find(|i| i.name().is_ok_and(|n| n == name))
         .ok_or(anyhow!("no matching result"))?;
 
     Ok(result)
@@ -0,0 +1,62 @@
+use std::{collections::BTreeMap, sync::Arc, sync::OnceLock};
+
+use crate::{AppState, config::config, games};
+
+/// every instance in the config, keyed by id.
+///
+/// instances can only be added or removed by restarting, so this is set once.
+static INSTANCES: OnceLock<BTreeMap<String, Arc<AppState>>> = OnceLock::new();
+
+/// creates an [`AppState`] for every instance in the config.
+pub fn init() {
+    let instances = config()
+        .instances
+        .iter()
+        .map(|(id, instance)| {
+            let game = games::get(&instance.server_type).expect("validated by the config");
+            let state = AppState::new(id.clone(), game, instance);
+            (id.clone(), Arc::new(state))
+        })
+        .collect();
+
+    INSTANCES
+        .set(instances)
+        .expect("instances are only created once");
+}
+
+pub fn all() -> impl Iterator<Item = &'static Arc<AppState>> {
+    INSTANCES
+        .get()
+        .expect("instances are created at startup")
+        .values()
+}
+
+/// the instance served at the routes without `/instances/{id}`, if there is one.
+pub fn default() -> Option<&'static Arc<AppState>> {
+    let id = config().default_instance.clone()?;
+    INSTANCES
+        .get()
+        .expect("instances are created at startup")
+        .get(&id)
+}
+
+/// how many instances are running, including those starting or stopping.
+pub fn running() -> usize {
+    all()
+        .filter(|instance| instance.lifecycle.get().is_running())
+        .count()
+}
+
+/// reload the config, applying it to every instance.
+///
+/// the old config is kept if the new one has any problems.
+pub fn reload() -> Result<(), Vec<String>> {
+    let config = crate::config::reload()?;
+
+    for instance in all() {
+        instance.reconfigure(&config);
+    }
+
+    tracing::info!("reloaded config");
+    Ok(())
+}
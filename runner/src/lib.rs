@@ -1,26 +1,143 @@
+use std::iter;
+
+use children::get_children;
 use tracing::warn;
-// sends `SIGKILL` on unix, `WM_CLOSE` on windows.
-pub fn force_kill(pid: u32) {
+
+/// sends `SIGKILL` on unix, `TerminateProcess` on windows.
+///
+/// # Errors
+///
+/// Will error if the process could not be killed.
+pub fn force_kill(pid: u32) -> std::io::Result<()> {
     #[cfg(windows)]
     {
-        use windows_sys::Win32::System::Threading::{
-            OpenProcess, PROCESS_TERMINATE, TerminateProcess,
+        use windows_sys::Win32::{
+            Foundation::CloseHandle,
+            System::Threading::{OpenProcess, PROCESS_TERMINATE, TerminateProcess},
         };
 
         let handle = unsafe { OpenProcess(PROCESS_TERMINATE, false.into(), pid) };
+        if handle.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
         let res = unsafe { TerminateProcess(handle, 1) };
+        let err = std::io::Error::last_os_error();
+        unsafe { CloseHandle(handle) };
         if res == 0 {
-            let err = std::io::Error::last_os_error();
-            warn!("could not kill pid {pid}: {err}");
+            return Err(err);
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        let res = unsafe { libc::kill(pid.cast_signed(), libc::SIGKILL) };
+        if res == -1 {
+            return Err(std::io::Error::last_os_error());
         }
     }
 
+    Ok(())
+}
+
+/// asks `pid` to exit by sending `SIGTERM` on unix.
+///
+/// # Errors
+///
+/// Will error if the signal could not be sent, or always on windows, where there is no equivalent.
+pub fn terminate(pid: u32) -> std::io::Result<()> {
     #[cfg(unix)]
     {
-        let res = unsafe { libc::kill(pid, libc::SIGKILL) };
+        let res = unsafe { libc::kill(pid.cast_signed(), libc::SIGTERM) };
+        if res == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "cannot terminate a process gracefully on windows",
+        ))
+    }
+}
+
+/// asks `pid` to exit by sending `SIGINT` on unix, or `CTRL_BREAK_EVENT` on windows.
+///
+/// on windows, `pid` must have been spawned with `CREATE_NEW_PROCESS_GROUP`, or the event reaches us too.
+///
+/// # Errors
+///
+/// Will error if the signal could not be sent.
+pub fn interrupt(pid: u32) -> std::io::Result<()> {
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Console::{CTRL_BREAK_EVENT, GenerateConsoleCtrlEvent};
+
+        let res = unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) };
+        if res == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        let res = unsafe { libc::kill(pid.cast_signed(), libc::SIGINT) };
+        if res == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+/// force kills `pid` and every one of its descendants, returning the pids that were killed.
+///
+/// on unix, the process group led by `pid` is also killed, catching anything spawned after we looked.
+pub fn kill_tree(pid: u32) -> Vec<u32> {
+    // look before killing anything, or the descendants will be reparented and lost.
+    let descendants = get_children(pid).unwrap_or_else(|err| {
+        warn!("could not get children of pid {pid}: {err}");
+        Vec::new()
+    });
+
+    let mut killed = Vec::with_capacity(descendants.len() + 1);
+    // kill the parent first so it can't spawn anything else.
+    for pid in iter::once(pid).chain(descendants.iter().map(|p| p.pid)) {
+        match force_kill(pid) {
+            Ok(()) => killed.push(pid),
+            Err(err) => warn!("could not kill pid {pid}: {err}"),
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        let res = unsafe { libc::killpg(pid.cast_signed(), libc::SIGKILL) };
         if res == -1 {
             let err = std::io::Error::last_os_error();
-            warn!("could not kill pid {pid}: {err}");
+            tracing::debug!("could not kill process group {pid}: {err}");
         }
     }
+
+    killed
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn kill_tree() {
+        let (mut root, children) = children::spawn_test_tree();
+        let root_pid = root.id();
+        assert_eq!(children.len(), 3);
+
+        let killed = super::kill_tree(root_pid);
+        let _ = root.wait();
+
+        assert_eq!(killed.len(), 4);
+        assert_eq!(killed[0], root_pid);
+        assert!(children.iter().all(|c| killed.contains(&c.pid)));
+    }
 }
@@ -0,0 +1,170 @@
+use std::{
+    fmt::Display,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use common::{ServerState, Transition};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// the current [`ServerState`] and when it was entered.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Status {
+    pub state: ServerState,
+    pub since: SystemTime,
+}
+
+#[derive(Debug)]
+pub struct InvalidTransition {
+    pub from: ServerState,
+    pub to: ServerState,
+}
+
+impl Display for InvalidTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server cannot go from {:?} to {:?}", self.from, self.to)
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+#[derive(Debug)]
+struct Inner {
+    status: Status,
+    /// how long the server was `Starting` before it was `Ready`.
+    startup_duration: Option<Duration>,
+}
+
+/// tracks the [`ServerState`], only allowing valid transitions.
+#[derive(Debug)]
+pub struct Lifecycle {
+    inner: Mutex<Inner>,
+    transitions: broadcast::Sender<Transition>,
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        let (transitions, _rx) = broadcast::channel(16);
+        Self {
+            inner: Mutex::new(Inner {
+                status: Status {
+                    state: ServerState::Stopped,
+                    since: SystemTime::now(),
+                },
+                startup_duration: None,
+            }),
+            transitions,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        self.inner.lock().expect("not poisoned").status
+    }
+
+    /// how long the server took to become ready after it started, if it has.
+    pub fn startup_duration(&self) -> Option<Duration> {
+        self.inner.lock().expect("not poisoned").startup_duration
+    }
+
+    pub fn get(&self) -> ServerState {
+        self.status().state
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Transition> {
+        self.transitions.subscribe()
+    }
+
+    /// go to `to` and broadcast it, if it is a valid transition from the current state.
+    pub fn transition(&self, to: ServerState) -> Result<Transition, InvalidTransition> {
+        let transition = {
+            let mut inner = self.inner.lock().expect("not poisoned");
+            let from = inner.status.state;
+            if !from.can_become(to) {
+                return Err(InvalidTransition { from, to });
+            }
+
+            let at = SystemTime::now();
+            match (from, to) {
+                (_, ServerState::Starting) => inner.startup_duration = None,
+                (ServerState::Starting, ServerState::Ready) => {
+                    inner.startup_duration = at.duration_since(inner.status.since).ok();
+                }
+                _ => {}
+            }
+            inner.status = Status {
+                state: to,
+                since: at,
+            };
+
+            Transition { from, to, at }
+        };
+
+        tracing::info!("server {:?} -> {:?}", transition.from, transition.to);
+        // no receivers is fine.
+        let _ = self.transitions.send(transition);
+
+        Ok(transition)
+    }
+
+    /// waits until the state satisfies `pred`, returning that state.
+    pub async fn wait_for(&self, pred: impl Fn(ServerState) -> bool) -> ServerState {
+        // subscribe first so we can't miss a transition between checking and waiting.
+        let mut rx = self.subscribe();
+
+        let current = self.get();
+        if pred(current) {
+            return current;
+        }
+
+        loop {
+            match rx.recv().await {
+                Ok(transition) if pred(transition.to) => return transition.to,
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => {
+                    let current = self.get();
+                    if pred(current) {
+                        return current;
+                    }
+                }
+                // we own the sender, so this can't happen.
+                Err(RecvError::Closed) => return self.get(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::ServerState;
+
+    use super::Lifecycle;
+
+    #[test]
+    fn transitions() {
+        let lifecycle = Lifecycle::new();
+        let mut rx = lifecycle.subscribe();
+
+        assert!(lifecycle.transition(ServerState::Ready).is_err());
+        assert!(lifecycle.transition(ServerState::Stopping).is_err());
+
+        lifecycle.transition(ServerState::Starting).unwrap();
+        lifecycle.transition(ServerState::Ready).unwrap();
+        assert!(lifecycle.startup_duration().is_some());
+        assert!(lifecycle.transition(ServerState::Starting).is_err());
+        lifecycle.transition(ServerState::Crashed).unwrap();
+        assert_eq!(lifecycle.get(), ServerState::Crashed);
+
+        let sent: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|t| (t.from, t.to))
+            .collect();
+        assert_eq!(
+            sent,
+            [
+                (ServerState::Stopped, ServerState::Starting),
+                (ServerState::Starting, ServerState::Ready),
+                (ServerState::Ready, ServerState::Crashed),
+            ]
+        );
+    }
+}
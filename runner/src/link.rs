@@ -0,0 +1,322 @@
+use std::{fmt::Display, future, sync::Arc, time::Duration};
+
+use axum::{
+    Router,
+    body::Body,
+    extract::{WebSocketUpgrade, ws},
+    http::{Request, header},
+    response::Response,
+};
+use common::{
+    AuthLevel, ConsoleLine, HelperMessage, LINK_IDLE_TIMEOUT, RunnerMessage, StateUpdate,
+    decode_frame, encode_frame,
+};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use reqwest_websocket::{Message, Upgrade};
+use tokio::{
+    sync::{broadcast::error::RecvError, mpsc},
+    task::JoinSet,
+};
+use tower::ServiceExt;
+use tracing::instrument;
+
+use crate::{AppState, config::config, instances};
+
+/// how long to wait before reconnecting the first time, doubled for each failure in a row.
+const BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// the largest response body sent over the link, well under the websocket's 64MiB message limit.
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// keeps a link open to the helper in `[helper]`, serving `app` over it, and reconnecting if it drops.
+#[instrument(skip_all)]
+pub async fn link(app: Router) {
+    let client = reqwest::Client::new();
+    let mut backoff = BACKOFF;
+
+    loop {
+        // read every time, so a reload applies from the next connection.
+        let Some(helper) = &config().helper else {
+            tracing::info!("`[helper]` was removed, not linking");
+            return;
+        };
+        let url = helper
+            .url
+            .join(&format!("api/link/{}", helper.runner))
+            .expect("a runner id is a valid path");
+
+        let ws = async {
+            client
+                .get(url)
+                .header("token", &helper.token)
+                .timeout(Duration::from_secs(10))
+                .upgrade()
+                .send()
+                .await?
+                .into_websocket()
+                .await
+        };
+
+        match ws.await {
+            Ok(ws) => {
+                tracing::info!("linked to the helper at {}", helper.url);
+                backoff = BACKOFF;
+
+                let (sink, stream) = ws.split();
+                let sink = sink.with(|frame: Vec<u8>| {
+                    future::ready(Ok::<_, reqwest_websocket::Error>(Message::Binary(
+                        frame.into(),
+                    )))
+                });
+                let stream = stream.filter_map(|message| {
+                    future::ready(match message {
+                        Ok(Message::Binary(frame)) => Some(Some(frame.to_vec())),
+                        Ok(Message::Close { .. }) => Some(None),
+                        Ok(_) => None,
+                        Err(err) => {
+                            tracing::warn!("link closed: {err}");
+                            Some(None)
+                        }
+                    })
+                });
+                serve(sink, stream, app.clone()).await;
+            }
+            Err(err) => tracing::warn!("could not link to the helper: {err}"),
+        }
+
+        tracing::info!("relinking in {backoff:?}");
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// where the helper links to us, when we don't dial out to it.
+pub async fn accept(ws: WebSocketUpgrade, app: Router) -> Response {
+    ws.on_upgrade(|socket| async move {
+        tracing::info!("the helper linked to us");
+
+        let (sink, stream) = socket.split();
+        let sink = sink.with(|frame: Vec<u8>| {
+            future::ready(Ok::<_, axum::Error>(ws::Message::Binary(frame.into())))
+        });
+        let stream = stream.filter_map(|message| {
+            future::ready(match message {
+                Ok(ws::Message::Binary(frame)) => Some(Some(frame.to_vec())),
+                Ok(ws::Message::Close(_)) => Some(None),
+                Ok(_) => None,
+                Err(err) => {
+                    tracing::warn!("link closed: {err}");
+                    Some(None)
+                }
+            })
+        });
+        serve(sink, stream, app).await;
+
+        tracing::info!("the helper unlinked");
+    })
+}
+
+/// answers the helper's requests with `app`, and sends it every instance's stats, console and state, until the link
+/// drops.
+///
+/// `stream` has the frames the helper sends, and `None` when it closes the link. the link is also closed if the
+/// helper sends nothing, not even a ping, for [`LINK_IDLE_TIMEOUT`].
+async fn serve<S, R>(mut sink: S, stream: R, app: Router)
+where
+    S: Sink<Vec<u8>> + Unpin,
+    S::Error: Display,
+    R: Stream<Item = Option<Vec<u8>>>,
+{
+    let (tx, mut rx) = mpsc::channel::<RunnerMessage>(64);
+
+    let default_instance = instances::default().map(|instance| instance.id.clone());
+    if tx
+        .send(RunnerMessage::Linked { default_instance })
+        .await
+        .is_err()
+    {
+        return;
+    }
+    // aborted when the link drops, since a stopped server may not send anything that would show them it did.
+    let mut forwarders = JoinSet::new();
+    for instance in instances::all() {
+        forwarders.spawn(forward_stats(instance.clone(), tx.clone()));
+        forwarders.spawn(forward_console(instance.clone(), tx.clone()));
+        forwarders.spawn(forward_state(instance.clone(), tx.clone()));
+    }
+
+    let writer = async {
+        while let Some(message) = rx.recv().await {
+            let frame = match encode_frame(&message) {
+                Ok(frame) => frame,
+                Err(err) => {
+                    tracing::error!("failed to serialize {message:?}: {err}");
+                    continue;
+                }
+            };
+            if let Err(err) = sink.send(frame).await {
+                tracing::warn!("link closed: {err}");
+                break;
+            }
+        }
+    };
+
+    let reader = async {
+        let mut stream = std::pin::pin!(stream);
+        loop {
+            let Ok(frame) = tokio::time::timeout(LINK_IDLE_TIMEOUT, stream.next()).await else {
+                tracing::warn!(
+                    "heard nothing from the helper in {LINK_IDLE_TIMEOUT:?}, closing the link"
+                );
+                break;
+            };
+            let Some(Some(frame)) = frame else {
+                break;
+            };
+            match decode_frame::<HelperMessage>(&frame) {
+                Ok(HelperMessage::Request { id, path }) => {
+                    tokio::spawn(respond(app.clone(), id, path, tx.clone()));
+                }
+                Ok(HelperMessage::Ping) => {
+                    // if the channel is full, the helper is hearing from us anyway.
+                    let _ = tx.try_send(RunnerMessage::Pong);
+                }
+                Err(err) => {
+                    tracing::warn!("could not read the helper's message: {err}");
+                    break;
+                }
+            }
+        }
+    };
+
+    tokio::select! {
+        () = writer => {}
+        () = reader => {}
+    }
+}
+
+/// answers request `id` for `path` with `app`.
+async fn respond(app: Router, id: u64, path: String, tx: mpsc::Sender<RunnerMessage>) {
+    let message = match Request::get(&path).body(Body::empty()) {
+        Ok(request) => {
+            let response = app
+                .oneshot(request)
+                .await
+                .unwrap_or_else(|err| match err {});
+            let status = response.status().as_u16();
+            let content_type = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(ToString::to_string);
+            match axum::body::to_bytes(response.into_body(), MAX_BODY).await {
+                Ok(body) => RunnerMessage::Response {
+                    id,
+                    status,
+                    content_type,
+                    body: body.to_vec(),
+                },
+                Err(err) => {
+                    tracing::warn!("could not read the response to {path}: {err}");
+                    RunnerMessage::Response {
+                        id,
+                        status: 500,
+                        content_type: None,
+                        body: b"failed to read the response".to_vec(),
+                    }
+                }
+            }
+        }
+        Err(err) => {
+            tracing::warn!("bad request path {path:?}: {err}");
+            RunnerMessage::Response {
+                id,
+                status: 400,
+                content_type: None,
+                body: b"bad request path".to_vec(),
+            }
+        }
+    };
+
+    // the link dropped, so no one is waiting.
+    let _ = tx.send(message).await;
+}
+
+async fn forward_stats(instance: Arc<AppState>, tx: mpsc::Sender<RunnerMessage>) {
+    let mut stats = instance.stats_channel.subscribe();
+    loop {
+        match stats.recv().await {
+            Ok(stats) => {
+                let message = RunnerMessage::Stats {
+                    instance: instance.id.clone(),
+                    stats,
+                };
+                if tx.send(message).await.is_err() {
+                    return;
+                }
+            }
+            Err(RecvError::Lagged(_)) => {}
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+async fn forward_console(instance: Arc<AppState>, tx: mpsc::Sender<RunnerMessage>) {
+    let mut console = instance.console_channel.subscribe();
+    loop {
+        match console.recv().await {
+            Ok(line) => {
+                let config = config();
+                let mut filtered = ConsoleLine::default();
+                for level in AuthLevel::ALL {
+                    filtered.set(level, config.filter(instance.game, level).apply(&line));
+                }
+                let message = RunnerMessage::Console {
+                    instance: instance.id.clone(),
+                    line: filtered,
+                };
+                if tx.send(message).await.is_err() {
+                    return;
+                }
+            }
+            Err(RecvError::Lagged(lag)) => tracing::debug!("link console lagged {lag} lines"),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+/// sends the current state, then every change of it or the startup progress.
+async fn forward_state(instance: Arc<AppState>, tx: mpsc::Sender<RunnerMessage>) {
+    let mut transitions = instance.lifecycle.subscribe();
+    let mut progress = instance.progress.subscribe();
+
+    loop {
+        let status = instance.lifecycle.status();
+        let update = StateUpdate {
+            state: status.state,
+            since: status.since,
+            progress: progress.borrow_and_update().clone(),
+        };
+        let message = RunnerMessage::State {
+            instance: instance.id.clone(),
+            update,
+        };
+        if tx.send(message).await.is_err() {
+            return;
+        }
+
+        tokio::select! {
+            transition = transitions.recv() => {
+                if let Err(RecvError::Closed) = transition {
+                    return;
+                }
+            }
+            changed = progress.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -1,27 +1,40 @@
+mod auth;
+mod config;
+mod countdown;
+mod crash;
+mod filters;
 mod games;
+mod instances;
+mod lifecycle;
+mod link;
+mod persist;
+mod restart;
 mod routes;
+mod stopping;
 mod tasks;
 
 use std::{
     env,
     fmt::Debug,
-    net::{Ipv4Addr, SocketAddrV4},
+    net::SocketAddr,
     path::PathBuf,
+    process,
     str::FromStr,
     sync::{
-        Arc, LazyLock,
+        Arc,
         atomic::{AtomicBool, AtomicU32, Ordering},
     },
     time::{Duration, SystemTime},
 };
 
-use axum::{Router, http::StatusCode, routing::get};
-use common::Stats;
+use axum::{Router, http::StatusCode, middleware, routing::get};
+use common::{Progress, ServerState, Stats};
+use runner::kill_tree;
 use serde::Serialize;
 use tokio::{
     net::TcpListener,
-    sync::{RwLock, broadcast},
-    task,
+    sync::{RwLock, broadcast, watch},
+    task::{self, AbortHandle},
 };
 use tower_http::timeout::TimeoutLayer;
 use tracing::{Level, level_filters::LevelFilter};
@@ -29,8 +42,16 @@ use tracing_subscriber::{
     EnvFilter, filter::Targets, layer::SubscriberExt, util::SubscriberInitExt,
 };
 
-use crate::games::Mod;
-use crate::routes::{console, exec, info, ip, list, ping, running, start, stats, stop};
+use crate::config::{InstanceConfig, config};
+use crate::crash::ConsoleTail;
+use crate::games::{Game, Mod};
+use crate::lifecycle::Lifecycle;
+use crate::restart::{RestartPolicy, Restarter, ServerExit};
+use crate::routes::{
+    capabilities, console, crashes, exec, info, instances as list_instances, ip, last_exit, list,
+    ping, progress, reload, restart, running, start, state, stats, stop,
+};
+use crate::stopping::StopPolicy;
 
 #[cfg(not(windows))]
 #[global_allocator]
@@ -40,20 +61,38 @@ static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
+/// one game server instance, which its routes are given.
 struct AppState {
+    /// its key in `[instances]`.
+    id: String,
     client: reqwest::Client,
+    /// the instance's game.
+    game: &'static dyn Game,
     stats_channel: broadcast::Sender<Stats>,
     console_channel: broadcast::Sender<String>,
+    /// the last lines of the server's output, cleared when the server starts.
+    console_tail: ConsoleTail,
+    lifecycle: Lifecycle,
+    /// only set while the server is starting.
+    progress: watch::Sender<Option<Progress>>,
     /// 0 if server is not running.
     server_pid: AtomicU32,
-    /// the server is starting up.
-    server_starting: AtomicBool,
-    /// the server is actively running.
-    server_running: AtomicBool,
-    /// the server is requested to be stopped
-    server_stopping: AtomicBool,
+    /// the pid of the process we spawned, which may be a wrapper around `server_pid`.
+    ///
+    /// 0 if server is not running.
+    server_root_pid: AtomicU32,
+    /// whether the running server was started by a previous runner, so its console is not ours.
+    adopted: AtomicBool,
     server_stdin: broadcast::Sender<String>,
+    /// the detected variant of the running server, eg. `Forge`.
+    server_variant: std::sync::Mutex<Option<String>>,
     server_info: RwLock<Option<ServerInfo>>,
+    /// `None` if the server has not exited since the runner started.
+    last_exit: RwLock<Option<ServerExit>>,
+    restarter: Restarter,
+    stop_policy: std::sync::RwLock<StopPolicy>,
+    /// the pending delayed stop or restart.
+    countdown: std::sync::Mutex<Option<AbortHandle>>,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -63,91 +102,176 @@ struct ServerInfo {
     // includes variant
     version: String,
     mods: Vec<Mod>,
+    /// how long the server took to become joinable, filled in once it has.
+    startup_duration: Option<Duration>,
 }
 
 impl Debug for AppState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AppState")
+            .field("id", &self.id)
+            .field("game", &self.game.name())
+            .field("lifecycle", &self.lifecycle)
+            .field("progress", &self.progress)
             .field("server_pid", &self.server_pid)
-            .field("server_starting", &self.server_starting)
-            .field("server_running", &self.server_running)
-            .field("server_stopping", &self.server_stopping)
+            .field("server_root_pid", &self.server_root_pid)
+            .field("adopted", &self.adopted)
             .field("server_info", &self.server_info)
+            .field("last_exit", &self.last_exit)
+            .field("restarter", &self.restarter)
             .finish_non_exhaustive()
     }
 }
 
 impl AppState {
-    fn new(
-        stats: broadcast::Sender<Stats>,
-        console: broadcast::Sender<String>,
-        stdin: broadcast::Sender<String>,
-    ) -> Self {
+    fn new(id: String, game: &'static dyn Game, instance: &InstanceConfig) -> Self {
+        let (restart_policy, stop_policy) = policies(game, instance);
+        tracing::debug!("{id}: restart policy: {restart_policy:?}, stop policy: {stop_policy:?}");
+
         AppState {
+            id,
             client: reqwest::Client::new(),
-            stats_channel: stats,
-            console_channel: console,
-            server_starting: AtomicBool::new(false),
-            server_running: AtomicBool::new(false),
-            server_stopping: AtomicBool::new(false),
+            game,
+            stats_channel: broadcast::channel(16).0,
+            // servers print a lot while starting up, and the readiness probe must not miss lines.
+            console_channel: broadcast::channel(256).0,
+            console_tail: ConsoleTail::new(config().crash.console_lines),
+            lifecycle: Lifecycle::new(),
+            progress: watch::Sender::new(None),
             server_pid: AtomicU32::new(0),
-            server_stdin: stdin,
+            server_root_pid: AtomicU32::new(0),
+            adopted: AtomicBool::new(false),
+            server_stdin: broadcast::channel(16).0,
+            server_variant: std::sync::Mutex::new(None),
             server_info: RwLock::new(None),
+            last_exit: RwLock::new(None),
+            restarter: Restarter::new(restart_policy),
+            stop_policy: std::sync::RwLock::new(stop_policy),
+            countdown: std::sync::Mutex::new(None),
         }
     }
 
-    /// declare that the server is stopped.
-    #[inline]
-    async fn set_stopped(&self) {
+    /// declare that the server is no longer running, going to `next` (`Stopped` or `Crashed`).
+    async fn set_stopped(&self, next: ServerState) {
         self.server_pid.store(0, Ordering::Release);
-        self.server_running.store(false, Ordering::Release);
+        self.server_root_pid.store(0, Ordering::Release);
+        self.adopted.store(false, Ordering::Release);
+        self.server_variant.lock().expect("not poisoned").take();
         self.server_info.write().await.take();
-    }
-}
+        persist::clear(&self.id);
 
-pub static SERVER_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
-    let dir = env::var("SERVER_DIR").expect("no SERVER_DIR environment variable");
-    let path = PathBuf::from(dir);
+        if let Err(err) = self.lifecycle.transition(next) {
+            tracing::error!("{err}");
+        }
+    }
 
-    assert!(path.exists(), "mc server dir does not exist");
+    /// this instance's part of the current config.
+    fn config(&self) -> Arc<InstanceConfig> {
+        config().instances[&self.id].clone()
+    }
 
-    path
-});
+    /// apply a reloaded config to the running policies.
+    fn reconfigure(&self, config: &config::Config) {
+        let (restart_policy, stop_policy) = policies(self.game, &config.instances[&self.id]);
+        tracing::debug!(
+            "{}: restart policy: {restart_policy:?}, stop policy: {stop_policy:?}",
+            self.id
+        );
+        self.restarter.set_policy(restart_policy);
+        *self.stop_policy.write().expect("not poisoned") = stop_policy;
+        self.console_tail.set_capacity(config.crash.console_lines);
+    }
 
-#[derive(Debug, PartialEq)]
-pub enum ServerType {
-    Minecraft,
-    Terraria,
-    Satisfactory,
-}
+    /// force kill the whole server process tree.
+    fn kill_server(&self) {
+        let root_pid = self.server_root_pid.load(Ordering::Relaxed);
+        let pid = if root_pid == 0 {
+            self.server_pid.load(Ordering::Relaxed)
+        } else {
+            root_pid
+        };
 
-impl FromStr for ServerType {
-    type Err = ();
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "minecraft" => Ok(Self::Minecraft),
-            "terraria" => Ok(Self::Terraria),
-            "satisfactory" => Ok(Self::Satisfactory),
-            _ => Err(()),
+        if pid == 0 {
+            tracing::error!("could not force kill because pid is 0");
+            return;
         }
+
+        let killed = kill_tree(pid);
+        tracing::warn!("force killed server process tree: {killed:?}");
     }
 }
 
-pub static SERVER_TYPE: LazyLock<ServerType> = LazyLock::new(|| {
-    env::var("SERVER_TYPE").map_or(ServerType::Minecraft, |v| {
-        v.parse().expect("server type not a valid value")
-    })
-});
+/// `game`'s policies, overridden by the `instance`'s config.
+fn policies(game: &dyn Game, instance: &InstanceConfig) -> (RestartPolicy, StopPolicy) {
+    (
+        game.restart_policy().with_config(&instance.restart),
+        game.stop_policy().with_config(&instance.stop),
+    )
+}
+
+/// the routes of one instance, nested at `/instances/{id}`.
+fn instance_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/start", get(start::start))
+        .route("/stop", get(stop::stop))
+        .route("/restart", get(restart))
+        .route("/running", get(running))
+        .route("/state", get(state))
+        .route("/progress", get(progress))
+        .route("/ip", get(ip))
+        .route("/list", get(list))
+        .route("/exec/{*cmd}", get(exec))
+        .route("/stats", get(stats))
+        .route("/console", get(console))
+        .route("/info", get(info))
+        .route("/last_exit", get(last_exit))
+        .route("/crashes", get(crashes::list))
+        .route("/crashes/{id}/{file}", get(crashes::download))
+        .route("/capabilities", get(capabilities))
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() == 3 && args[1] == "--wd" {
-        env::set_current_dir(&args[2]).expect("failed to set working dir");
+    let mut config_path = None;
+    let mut check_config = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wd" => {
+                let wd = args.next().expect("`--wd` needs a directory");
+                env::set_current_dir(wd).expect("failed to set working dir");
+            }
+            "--config" => config_path = Some(args.next().expect("`--config` needs a path")),
+            "--check-config" => check_config = true,
+            arg => {
+                eprintln!("unknown argument `{arg}`");
+                process::exit(2);
+            }
+        }
     }
 
-    if let Err(err) = dotenvy::dotenv() {
-        eprintln!("could not load .env: {err}");
+    common::config::load_dotenv();
+
+    // a config file that was asked for must exist.
+    let config_path = config_path.or_else(|| env::var("RUNNER_CONFIG").ok());
+    let required = config_path.is_some();
+    let config_path =
+        PathBuf::from(config_path.unwrap_or_else(|| config::DEFAULT_PATH.to_string()));
+
+    match config::load(&config_path, required) {
+        Ok(_) if check_config => {
+            println!("{} is valid", config_path.display());
+            return Ok(());
+        }
+        Ok(config) => config::init(config_path, required, config),
+        Err(problems) => {
+            eprintln!("invalid config ({}):", config_path.display());
+            for problem in problems {
+                eprintln!("- {problem}");
+            }
+            process::exit(1);
+        }
     }
 
     let filter = env::var(EnvFilter::DEFAULT_ENV).map_or(LevelFilter::INFO, |e| {
@@ -161,63 +285,71 @@ async fn main() -> anyhow::Result<()> {
         .with(Targets::new().with_target(env!("CARGO_PKG_NAME"), filter))
         .init();
 
-    let server_type = &*SERVER_TYPE;
+    instances::init();
 
-    let (stats_tx, _rx) = broadcast::channel(16);
-    let (console_tx, _rx) = broadcast::channel(16);
-    let (stdin_tx, _rx) = broadcast::channel(16);
-    let app_state = Arc::new(AppState::new(stats_tx, console_tx, stdin_tx));
-
-    let app = Router::new()
-        .route("/start", get(start::start))
-        .route("/stop", get(stop))
-        .route("/running", get(running))
-        .route("/ip", get(ip))
+    let mut app = Router::new()
         .route("/ping", get(ping))
-        .route("/list", get(list))
-        .route("/exec/{*cmd}", get(exec))
-        .route("/stats", get(stats))
-        .route("/console", get(console))
-        .route("/info", get(info))
-        .with_state(app_state.clone())
-        .layer(TimeoutLayer::with_status_code(
-            StatusCode::REQUEST_TIMEOUT,
-            Duration::from_secs(5),
-        ));
-
-    task::spawn_blocking({
-        let app_state = app_state.clone();
-        move || tasks::stats_refresher(&app_state)
-    });
+        .route("/reload", get(reload))
+        .route("/instances", get(list_instances));
+    for instance in instances::all() {
+        app = app.nest(
+            &format!("/instances/{}", instance.id),
+            instance_routes().with_state(instance.clone()),
+        );
+    }
+    // so clients that only know one server keep working.
+    if let Some(instance) = instances::default() {
+        app = app.merge(instance_routes().with_state(instance.clone()));
+    }
+    let app = app.layer(TimeoutLayer::with_status_code(
+        StatusCode::REQUEST_TIMEOUT,
+        Duration::from_secs(5),
+    ));
+    // what the helper is served over the link, which can't itself be linked to over it.
+    let linked = app.clone();
+    let app = app
+        .route(
+            "/link",
+            get({
+                let linked = linked.clone();
+                move |ws| link::accept(ws, linked)
+            }),
+        )
+        // requests over the link were authorized when it opened.
+        .layer(middleware::from_fn(auth::authorize));
 
-    let port =
-        env::var("RUNNER_PORT").map_or(4321, |p| p.parse().expect("configured port is not an int"));
-    let ip = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port);
-
-    let server_path = &*SERVER_PATH;
-
-    tracing::info!("running server on :{port}");
-    if server_type == &ServerType::Minecraft {
-        tracing::info!("");
-        let java_version = tokio::process::Command::new("java")
-            .arg("--version")
-            .output()
-            .await
-            .expect("`java --version` failed to run");
-        let java_version = str::from_utf8(&java_version.stdout).expect("stdout not utf8");
-        for line in java_version.split('\n') {
-            if !line.is_empty() {
-                tracing::info!("{line}");
-            }
+    for instance in instances::all() {
+        if persist::adopt(instance) {
+            tracing::info!(
+                "adopted the {} server left running by the previous runner",
+                instance.id
+            );
         }
-    } else if server_type == &ServerType::Terraria {
-        tracing::info!("terraria server set at {server_path:?}");
+    }
+    #[cfg(unix)]
+    tokio::spawn(tasks::reload_on_sighup());
+    if config().helper.is_some() {
+        tokio::spawn(link::link(linked));
+    }
+    task::spawn_blocking(tasks::stats_refresher);
+
+    let port = config().port;
+    let ip = SocketAddr::new(config().bind, port);
+
+    for instance in instances::all() {
+        instance.game.log_setup(&instance.config());
+    }
+    if config().token.is_none() {
+        tracing::warn!("no `token` is set, so anyone who can reach us can control the servers");
     }
 
     let listener = TcpListener::bind(ip).await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(tasks::shutdown(app_state))
-        .await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(tasks::shutdown())
+    .await?;
 
     Ok(())
 }
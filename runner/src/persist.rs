@@ -0,0 +1,287 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex, atomic::Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use common::ServerState;
+use serde::{Deserialize, Serialize};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+use crate::{
+    AppState,
+    config::{DEFAULT_INSTANCE, config},
+    tasks,
+};
+
+/// how far a process' start time may be from the recorded one, so a reused pid is not adopted.
+const START_TOLERANCE: u64 = 5;
+
+/// held while the state file is read and written, since every instance shares it.
+static STATE_FILE: Mutex<()> = Mutex::new(());
+
+/// the state file, with the running servers keyed by instance id.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateFile {
+    servers: BTreeMap<String, PersistedServer>,
+}
+
+/// a game server this runner spawned.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersistedServer {
+    pub server_type: String,
+    pub variant: String,
+    pub pid: u32,
+    pub root_pid: u32,
+    /// seconds since the unix epoch.
+    pub start_time: u64,
+}
+
+impl PersistedServer {
+    /// the currently running server.
+    pub fn current(state: &AppState, start_time: SystemTime) -> Self {
+        Self {
+            server_type: state.game.name().to_string(),
+            variant: state
+                .server_variant
+                .lock()
+                .expect("not poisoned")
+                .clone()
+                .unwrap_or_default(),
+            pid: state.server_pid.load(Ordering::Acquire),
+            root_pid: state.server_root_pid.load(Ordering::Acquire),
+            start_time: start_time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    /// write to the state file as instance `id`'s server.
+    pub fn save(self, id: &str) {
+        update(&config().state_file, |servers| {
+            servers.insert(id.to_string(), self);
+        });
+    }
+}
+
+/// every server in the state file at `path`.
+fn load(path: &Path) -> anyhow::Result<BTreeMap<String, PersistedServer>> {
+    /// older runners only saved one server.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Single(PersistedServer),
+        Many(StateFile),
+    }
+
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => return Err(err).context("reading"),
+    };
+
+    Ok(match toml::from_str(&contents).context("parsing")? {
+        Raw::Single(server) => BTreeMap::from([(DEFAULT_INSTANCE.to_string(), server)]),
+        Raw::Many(file) => file.servers,
+    })
+}
+
+/// applies `change` to the state file at `path`, logging any error.
+///
+/// the file is deleted once no servers are left in it.
+fn update(path: &Path, change: impl FnOnce(&mut BTreeMap<String, PersistedServer>)) {
+    let _lock = STATE_FILE.lock().expect("not poisoned");
+
+    let mut servers = load(path).unwrap_or_else(|err| {
+        tracing::warn!("could not load {path:?}, overwriting it: {err:#}");
+        BTreeMap::new()
+    });
+    change(&mut servers);
+
+    let res = if servers.is_empty() {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err).context("deleting"),
+            _ => Ok(()),
+        }
+    } else {
+        toml::to_string(&StateFile { servers })
+            .context("serializing")
+            .and_then(|s| fs::write(path, s).context("writing"))
+    };
+    if let Err(err) = res {
+        tracing::warn!("could not save {path:?}: {err:#}");
+    }
+}
+
+/// removes instance `id`'s server from the state file, since it is no longer running.
+pub fn clear(id: &str) {
+    update(&config().state_file, |servers| {
+        servers.remove(id);
+    });
+}
+
+/// when `pid` started in seconds since the unix epoch, if it is running.
+pub fn process_start_time(system: &mut System, pid: u32) -> Option<u64> {
+    let pid = Pid::from_u32(pid);
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+    system.process(pid).map(sysinfo::Process::start_time)
+}
+
+/// whether `pid` is running and started at `start_time`, so it is not another process that reused the pid.
+fn is_running(system: &mut System, pid: u32, start_time: u64) -> bool {
+    process_start_time(system, pid)
+        .is_some_and(|started| started.abs_diff(start_time) <= START_TOLERANCE)
+}
+
+/// re-attaches to the game server recorded in the state file if it is still running, returning whether it was.
+///
+/// the adopted server's console is not available, since its stdio belonged to the previous runner.
+/// on windows it is never adopted, since without its console it could only be force killed.
+pub fn adopt(state: &Arc<AppState>) -> bool {
+    let persisted = match load(&config().state_file) {
+        Ok(mut servers) => servers.remove(&state.id),
+        Err(err) => {
+            tracing::warn!("could not load {:?}: {err:#}", config().state_file);
+            None
+        }
+    };
+    let Some(persisted) = persisted else {
+        return false;
+    };
+
+    let server_type = state.game.name();
+    // case-insensitively, since older runners saved eg. `Minecraft`.
+    if !persisted.server_type.eq_ignore_ascii_case(server_type) {
+        tracing::warn!(
+            "not adopting {} server, configured for {server_type}",
+            persisted.server_type
+        );
+        clear(&state.id);
+        return false;
+    }
+
+    let mut system = System::new();
+    if !is_running(&mut system, persisted.root_pid, persisted.start_time) {
+        tracing::info!("previous server (pid {}) is gone", persisted.root_pid);
+        clear(&state.id);
+        return false;
+    }
+
+    if cfg!(windows) {
+        tracing::warn!(
+            "not adopting the previous server (pid {}), it could not be stopped gracefully on windows. \
+             stop it by hand before starting another",
+            persisted.root_pid
+        );
+        clear(&state.id);
+        return false;
+    }
+
+    tracing::info!("adopting running server: {persisted:?}");
+
+    let start_time = UNIX_EPOCH + Duration::from_secs(persisted.start_time);
+    let variant = state.game.adopt(state.clone(), start_time);
+    if variant.as_ref() != Some(&persisted.variant) {
+        tracing::warn!(
+            "server variant changed from {} to {variant:?} since it started",
+            persisted.variant
+        );
+    }
+    state
+        .server_variant
+        .lock()
+        .expect("not poisoned")
+        .clone_from(&variant);
+
+    state.server_pid.store(persisted.pid, Ordering::Release);
+    state
+        .server_root_pid
+        .store(persisted.root_pid, Ordering::Release);
+    state.adopted.store(true, Ordering::Release);
+
+    for next in [ServerState::Starting, ServerState::Ready] {
+        if let Err(err) = state.lifecycle.transition(next) {
+            tracing::error!("{err}");
+        }
+    }
+
+    tokio::spawn(tasks::adopted_observer(
+        state.clone(),
+        persisted.root_pid,
+        start_time,
+    ));
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs, process};
+
+    use sysinfo::System;
+
+    use super::{PersistedServer, START_TOLERANCE, is_running, load, process_start_time, update};
+
+    fn server(pid: u32) -> PersistedServer {
+        PersistedServer {
+            server_type: "minecraft".to_string(),
+            variant: "Paper".to_string(),
+            pid,
+            root_pid: pid,
+            start_time: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn save_load() {
+        let path = std::env::temp_dir().join(format!("runner-state-{}.toml", process::id()));
+        let _ = fs::remove_file(&path);
+        assert!(load(&path).unwrap().is_empty());
+
+        update(&path, |servers| {
+            servers.insert("mc".to_string(), server(1));
+            servers.insert("tml".to_string(), server(2));
+        });
+        update(&path, |servers| {
+            servers.remove("mc");
+        });
+        let servers = load(&path).unwrap();
+        assert_eq!(servers.keys().collect::<Vec<_>>(), ["tml"]);
+        assert_eq!(servers["tml"].root_pid, 2);
+
+        // deleted once empty.
+        update(&path, BTreeMap::clear);
+        assert!(!path.exists());
+
+        // older runners saved a single server.
+        fs::write(&path, toml::to_string(&server(3)).unwrap()).unwrap();
+        assert_eq!(load(&path).unwrap()["default"].pid, 3);
+
+        fs::write(&path, "not toml").unwrap();
+        assert!(load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reused_pid() {
+        let mut system = System::new();
+        let pid = process::id();
+        let started = process_start_time(&mut system, pid).unwrap();
+
+        assert!(is_running(&mut system, pid, started));
+        assert!(is_running(&mut system, pid, started + START_TOLERANCE));
+        assert!(is_running(&mut system, pid, started - START_TOLERANCE));
+        // another process that got the same pid.
+        assert!(!is_running(&mut system, pid, started - 60));
+        assert!(!is_running(&mut system, pid, started + START_TOLERANCE + 1));
+        assert!(!is_running(&mut system, u32::MAX, started));
+    }
+}
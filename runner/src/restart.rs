@@ -0,0 +1,275 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use common::ServerState;
+use serde::Serialize;
+use tokio::task::AbortHandle;
+
+use crate::{AppState, config::RestartConfig, routes::start::start_server};
+
+/// what to do when the game server crashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    pub enabled: bool,
+    /// how long to wait before the first restart, doubled for every restart since in `window`.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    /// give up after restarting this many times in `window`.
+    pub max_restarts: u32,
+    pub window: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(5 * 60),
+            max_restarts: 3,
+            window: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// overrides `self` with what is set in the config.
+    pub fn with_config(mut self, config: &RestartConfig) -> Self {
+        self.enabled = config.on_crash.unwrap_or(self.enabled);
+        self.max_restarts = config.max.unwrap_or(self.max_restarts);
+        self.backoff = config.backoff.map_or(self.backoff, Duration::from_secs);
+        self.max_backoff = config
+            .max_backoff
+            .map_or(self.max_backoff, Duration::from_secs);
+        self.window = config.window.map_or(self.window, Duration::from_secs);
+
+        self
+    }
+
+    /// how long to wait before restarting, after already restarting `restarts` times in the window.
+    fn delay(&self, restarts: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(restarts))
+            .min(self.max_backoff)
+    }
+}
+
+/// why the server last exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExitKind {
+    /// we asked it to stop.
+    Requested,
+    /// it stopped by itself without an error, eg. by `/stop` through `/exec`.
+    Clean,
+    /// it stopped by itself with an error, or we could not tell.
+    Crashed,
+    /// it was adopted, so it is not our child and we can't tell how it stopped.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerExit {
+    pub kind: ExitKind,
+    pub at: SystemTime,
+    /// `None` if the server was killed by a signal.
+    pub code: Option<i32>,
+    /// eg. `exit status: 1`
+    pub status: String,
+    /// when the server will be restarted, if it will.
+    pub restart_at: Option<SystemTime>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// when we restarted the server, oldest first.
+    restarts: VecDeque<Instant>,
+    pending: Option<AbortHandle>,
+}
+
+/// restarts the server after crashes according to its [`RestartPolicy`].
+#[derive(Debug)]
+pub struct Restarter {
+    policy: Mutex<RestartPolicy>,
+    inner: Mutex<Inner>,
+}
+
+impl Restarter {
+    pub fn new(policy: RestartPolicy) -> Self {
+        Self {
+            policy: Mutex::new(policy),
+            inner: Mutex::default(),
+        }
+    }
+
+    /// use `policy` for the next crash, keeping the restarts already made.
+    pub fn set_policy(&self, policy: RestartPolicy) {
+        *self.policy.lock().expect("not poisoned") = policy;
+    }
+
+    /// schedules a restart after a crash if the policy allows it, returning the delay.
+    pub fn schedule(&self, state: Arc<AppState>) -> Option<Duration> {
+        let delay = self.reserve(Instant::now())?;
+
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+
+            // it may have been started by hand in the meantime.
+            if state.lifecycle.get() != ServerState::Crashed {
+                tracing::info!("server no longer crashed, not restarting");
+                return;
+            }
+
+            tracing::info!("restarting crashed server");
+            let (status, msg) = start_server(state).await;
+            if !status.is_success() {
+                tracing::warn!("could not restart server: {msg}");
+            }
+        });
+        self.set_pending(task.abort_handle());
+
+        Some(delay)
+    }
+
+    /// counts a restart after a crash at `now` if the policy allows another, returning how long to wait before it.
+    fn reserve(&self, now: Instant) -> Option<Duration> {
+        let policy = *self.policy.lock().expect("not poisoned");
+        if !policy.enabled {
+            return None;
+        }
+
+        let mut inner = self.inner.lock().expect("not poisoned");
+
+        while inner
+            .restarts
+            .front()
+            .is_some_and(|&at| now.duration_since(at) > policy.window)
+        {
+            inner.restarts.pop_front();
+        }
+
+        let restarts = u32::try_from(inner.restarts.len()).unwrap_or(u32::MAX);
+        if restarts >= policy.max_restarts {
+            tracing::warn!(
+                "not restarting, already restarted {restarts} times in {:?}",
+                policy.window
+            );
+            return None;
+        }
+
+        let delay = policy.delay(restarts);
+        inner.restarts.push_back(now + delay);
+
+        tracing::info!(
+            "restarting server in {delay:?} ({} of {})",
+            restarts + 1,
+            policy.max_restarts
+        );
+
+        Some(delay)
+    }
+
+    /// `task` is the scheduled restart, replacing any other.
+    fn set_pending(&self, task: AbortHandle) {
+        let mut inner = self.inner.lock().expect("not poisoned");
+        if let Some(old) = inner.pending.replace(task) {
+            old.abort();
+        }
+    }
+
+    /// cancels a scheduled restart, returning whether there was one.
+    pub fn cancel(&self) -> bool {
+        let pending = self.inner.lock().expect("not poisoned").pending.take();
+        pending.is_some_and(|task| {
+            let cancelled = !task.is_finished();
+            task.abort();
+            cancelled
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{RestartPolicy, Restarter};
+
+    fn policy() -> RestartPolicy {
+        RestartPolicy {
+            enabled: true,
+            backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(60),
+            max_restarts: 3,
+            window: Duration::from_secs(30 * 60),
+        }
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = RestartPolicy {
+            backoff: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(60),
+            ..RestartPolicy::default()
+        };
+
+        let delays: Vec<_> = (0..6).map(|n| policy.delay(n).as_secs()).collect();
+        assert_eq!(delays, [5, 10, 20, 40, 60, 60]);
+        assert_eq!(policy.delay(u32::MAX), policy.max_backoff);
+    }
+
+    #[test]
+    fn max_restarts_in_window() {
+        let restarter = Restarter::new(policy());
+        let now = Instant::now();
+
+        let delays: Vec<_> = (0..4)
+            .map(|_| restarter.reserve(now).map(|d| d.as_secs()))
+            .collect();
+        assert_eq!(delays, [Some(5), Some(10), Some(20), None]);
+
+        // the restarts leave the window one by one, with the backoff of how many are left in it.
+        let later = now + policy().window + Duration::from_secs(6);
+        assert_eq!(restarter.reserve(later), Some(Duration::from_secs(20)));
+        assert_eq!(restarter.reserve(later), None);
+        let much_later = later + policy().window + Duration::from_secs(60);
+        assert_eq!(restarter.reserve(much_later), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn set_policy() {
+        let restarter = Restarter::new(RestartPolicy {
+            enabled: false,
+            ..policy()
+        });
+        let now = Instant::now();
+        assert_eq!(restarter.reserve(now), None);
+
+        // the restarts already made still count.
+        restarter.set_policy(policy());
+        assert_eq!(restarter.reserve(now), Some(Duration::from_secs(5)));
+        restarter.set_policy(RestartPolicy {
+            max_restarts: 1,
+            ..policy()
+        });
+        assert_eq!(restarter.reserve(now), None);
+    }
+
+    #[tokio::test]
+    async fn cancel() {
+        let restarter = Restarter::new(policy());
+        assert!(!restarter.cancel());
+
+        let first = tokio::spawn(std::future::pending::<()>());
+        restarter.set_pending(first.abort_handle());
+        // a new restart replaces the pending one.
+        let second = tokio::spawn(std::future::pending::<()>());
+        restarter.set_pending(second.abort_handle());
+        assert!(first.await.unwrap_err().is_cancelled());
+
+        assert!(restarter.cancel());
+        assert!(second.await.unwrap_err().is_cancelled());
+        assert!(!restarter.cancel());
+    }
+}
@@ -0,0 +1,19 @@
+use std::sync::atomic::Ordering;
+
+use axum::{Json, extract::State};
+use common::Capabilities;
+
+use super::AppState;
+
+/// what the instance's game and its variant support.
+pub async fn capabilities(State(state): AppState) -> Json<Capabilities> {
+    let mut capabilities = state.game.capabilities(&state.config());
+
+    // an adopted server's console belonged to the previous runner.
+    if state.adopted.load(Ordering::Acquire) {
+        capabilities.list = false;
+        capabilities.exec = false;
+    }
+
+    Json(capabilities)
+}
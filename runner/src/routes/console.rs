@@ -1,8 +1,3 @@
-use std::{
-    env,
-    sync::{LazyLock, atomic::Ordering},
-};
-
 use axum::{
     extract::{
         Query, State, WebSocketUpgrade,
@@ -11,12 +6,11 @@ use axum::{
     http::StatusCode,
     response::Response,
 };
+use common::AuthLevel;
 use serde::Deserialize;
 use tokio::sync::broadcast::{Receiver, error::RecvError};
 
-use crate::{SERVER_TYPE, ServerType, routes::AppState};
-
-pub static SECRET: LazyLock<Option<String>> = LazyLock::new(|| env::var("SECRET").ok());
+use crate::{config::config, games::Game, routes::AppState};
 
 #[derive(Deserialize)]
 pub struct Secret {
@@ -28,21 +22,33 @@ pub async fn console(
     State(state): AppState,
     secret: Query<Secret>, // safe because users only have access to `helper`, which is unable to set the query string.
 ) -> Result<Response, StatusCode> {
-    if !state.server_running.load(Ordering::Relaxed) {
+    if !state.lifecycle.get().is_running() {
         return Err(StatusCode::SERVICE_UNAVAILABLE);
     }
 
     let secret = secret.secret.as_ref();
-    let no_filter = SECRET.as_ref().is_some_and(|s| Some(s) == secret);
+    let no_filter = config().secret.as_ref().is_some_and(|s| Some(s) == secret);
     let channel = state.clone().console_channel.subscribe();
-    Ok(ws.on_upgrade(move |socket| handle_socket(socket, channel, no_filter)))
+    let game = state.game;
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, channel, game, no_filter)))
 }
 
-async fn handle_socket(mut socket: WebSocket, mut channel: Receiver<String>, no_filter: bool) {
+async fn handle_socket(
+    mut socket: WebSocket,
+    mut channel: Receiver<String>,
+    game: &'static dyn Game,
+    no_filter: bool,
+) {
     loop {
         match channel.recv().await {
             Ok(line) => {
-                let line = if no_filter { line } else { filter_line(line) };
+                let line = if no_filter {
+                    line
+                } else if let Some(line) = config().filter(game, AuthLevel::Unauthed).apply(&line) {
+                    line
+                } else {
+                    continue;
+                };
 
                 if let Err(err) = socket.send(Message::text(line)).await {
                     tracing::warn!("{err}, closing socket");
@@ -59,29 +65,3 @@ async fn handle_socket(mut socket: WebSocket, mut channel: Receiver<String>, no_
         }
     }
 }
-
-// TODO: can be extended to a filters module
-#[inline]
-fn mc_from_list(line: &str) -> bool {
-    let split: Vec<&str> = line.split(']').collect();
-
-    // `[12:34:56 INFO]: There are`
-    let paper = || split.get(1).is_some_and(|l| l.starts_with(": There are"));
-    // `[12:34:56] [Server thread/INFO] [minecraft/MinecraftServer]: There are`
-    let forge = || split.get(3).is_some_and(|l| l.starts_with(": There are"));
-
-    paper() || forge()
-}
-
-#[inline]
-fn filter_line(line: String) -> String {
-    // its from /list, safe to send raw.
-    if *SERVER_TYPE == ServerType::Minecraft && mc_from_list(&line) {
-        return line;
-    }
-
-    // hide ips and coords
-    line.chars()
-        .map(|char| if char.is_ascii_digit() { '*' } else { char })
-        .collect()
-}
@@ -0,0 +1,37 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use tokio::task;
+
+use super::AppState;
+use crate::crash::{self, CrashReport};
+
+/// lists the instance's saved crash reports, newest first.
+pub async fn list(State(state): AppState) -> Json<Vec<CrashReport>> {
+    let instance = state.config();
+    let reports = task::spawn_blocking(move || crash::list(&instance.crash_dir)).await;
+    Json(reports.unwrap_or_default())
+}
+
+/// downloads one file of a crash report.
+pub async fn download(
+    State(state): AppState,
+    Path((id, file)): Path<(String, String)>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let Some(path) = crash::file_path(&state.config().crash_dir, &id, &file) else {
+        return Err((StatusCode::BAD_REQUEST, "invalid crash report file"));
+    };
+
+    let Ok(contents) = tokio::fs::read(path).await else {
+        return Err((StatusCode::NOT_FOUND, "no such crash report file"));
+    };
+
+    // every crash artifact is a text log.
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        contents,
+    ))
+}
@@ -5,11 +5,18 @@ use reqwest::StatusCode;
 
 use super::AppState;
 
-/// NOT meant to be accessible publicly.
+/// only meant to be reached through the helper, which checks who may run what.
 pub async fn exec(Path(cmd): Path<String>, State(state): AppState) -> (StatusCode, &'static str) {
-    if !state.server_running.load(Ordering::Relaxed) {
+    if !state.lifecycle.get().is_running() {
         return (StatusCode::SERVICE_UNAVAILABLE, "server not on!");
     }
+    // an adopted server's console belongs to the previous runner.
+    if state.adopted.load(Ordering::Acquire) {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "server console not available!",
+        );
+    }
 
     if let Err(err) = state.server_stdin.send(cmd) {
         tracing::info!("failed to send cmd: {err}");
@@ -4,9 +4,10 @@ use super::AppState;
 use crate::ServerInfo;
 
 pub async fn info(State(state): AppState) -> Result<Json<ServerInfo>, StatusCode> {
-    let Some(info) = state.server_info.read().await.clone() else {
+    let Some(mut info) = state.server_info.read().await.clone() else {
         return Err(StatusCode::SERVICE_UNAVAILABLE);
     };
+    info.startup_duration = state.lifecycle.startup_duration();
 
     Ok(Json(info))
 }
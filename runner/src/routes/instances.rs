@@ -0,0 +1,19 @@
+use axum::Json;
+use common::InstanceSummary;
+
+use crate::{config::config, instances as all};
+
+/// lists every instance and whether its server is running.
+pub async fn instances() -> Json<Vec<InstanceSummary>> {
+    let default = config().default_instance.clone();
+    let instances = all::all()
+        .map(|instance| InstanceSummary {
+            id: instance.id.clone(),
+            server_type: instance.game.name().to_string(),
+            state: instance.lifecycle.get(),
+            default: default.as_ref() == Some(&instance.id),
+        })
+        .collect();
+
+    Json(instances)
+}
@@ -0,0 +1,13 @@
+use axum::{Json, extract::State, http::StatusCode};
+
+use super::AppState;
+use crate::restart::ServerExit;
+
+/// returns why and when the server last exited, if it has since the runner started.
+pub async fn last_exit(State(state): AppState) -> Result<Json<ServerExit>, StatusCode> {
+    let Some(exit) = state.last_exit.read().await.clone() else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    Ok(Json(exit))
+}
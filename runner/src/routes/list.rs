@@ -1,23 +1,18 @@
-use std::sync::atomic::Ordering;
-
 use axum::extract::State;
 use reqwest::StatusCode;
 
 use super::AppState;
-use crate::{SERVER_TYPE, ServerType};
 
 pub async fn list(State(state): AppState) -> (StatusCode, &'static str) {
-    if !state.server_running.load(Ordering::Relaxed) {
+    if !state.lifecycle.get().is_running() {
         return (StatusCode::SERVICE_UNAVAILABLE, "server not on!");
     }
 
-    // TODO: should be handled better
-
-    if *SERVER_TYPE == ServerType::Satisfactory {
+    let Some(list_command) = state.game.list_command(&state.config()) else {
         return (StatusCode::NOT_IMPLEMENTED, "unsupported");
-    }
+    };
 
-    if let Err(err) = state.server_stdin.send("/list".to_string()) {
+    if let Err(err) = state.server_stdin.send(list_command) {
         tracing::info!("failed to send cmd: {err}");
     }
 
@@ -2,12 +2,20 @@ type AppState = axum::extract::State<std::sync::Arc<crate::AppState>>;
 
 pub mod start;
 
-mod stop;
-pub use stop::stop;
+pub mod stop;
+
+mod restart;
+pub use restart::restart;
 
 mod running;
 pub use running::running;
 
+mod state;
+pub use state::state;
+
+mod progress;
+pub use progress::progress;
+
 mod stats;
 pub use stats::stats;
 
@@ -29,6 +37,20 @@ pub use console::console;
 mod info;
 pub use info::info;
 
+mod last_exit;
+pub use last_exit::last_exit;
+
+pub mod crashes;
+
+mod reload;
+pub use reload::reload;
+
+mod capabilities;
+pub use capabilities::capabilities;
+
+mod instances;
+pub use instances::instances;
+
 /// warn `msg`, then return an `INTERNAL_SERVER_ERROR` with `msg`
 #[macro_export]
 macro_rules! warn_error {
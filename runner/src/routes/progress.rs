@@ -0,0 +1,13 @@
+use axum::{Json, extract::State, http::StatusCode};
+use common::Progress;
+
+use super::AppState;
+
+/// returns the server's startup progress, only available while it is starting.
+pub async fn progress(State(state): AppState) -> Result<Json<Progress>, StatusCode> {
+    let Some(progress) = state.progress.borrow().clone() else {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    };
+
+    Ok(Json(progress))
+}
@@ -0,0 +1,17 @@
+use axum::http::StatusCode;
+
+use crate::instances;
+
+/// reloads the config for every instance, listing the problems if it was not reloaded.
+pub async fn reload() -> (StatusCode, String) {
+    match instances::reload() {
+        Ok(()) => (StatusCode::OK, "reloaded config!".to_string()),
+        Err(problems) => {
+            tracing::warn!("not reloading config: {problems:?}");
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("not reloaded:\n- {}", problems.join("\n- ")),
+            )
+        }
+    }
+}
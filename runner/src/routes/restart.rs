@@ -0,0 +1,68 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::extract::{Query, State};
+use reqwest::StatusCode;
+
+use super::{
+    AppState,
+    start::start_server,
+    stop::{Delay, begin_stop},
+};
+use crate::{countdown, stopping::StopStep};
+
+/// stops the server after counting down `delay`, then starts it again once it has stopped.
+pub async fn restart(
+    State(state): AppState,
+    Query(Delay { delay }): Query<Delay>,
+) -> (StatusCode, &'static str) {
+    if !state.lifecycle.get().is_running() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "server not on!");
+    }
+
+    if delay == 0 {
+        if countdown::cancel(&state) {
+            tracing::info!("cancelled countdown, restarting now");
+        }
+        tokio::spawn(restart_server(state));
+        return (StatusCode::OK, "restarting!");
+    }
+
+    if countdown::spawn(
+        &state,
+        "restarting",
+        Duration::from_secs(delay),
+        restart_server,
+    ) {
+        (StatusCode::OK, "restarting soon!")
+    } else {
+        (StatusCode::TOO_MANY_REQUESTS, "already counting down!")
+    }
+}
+
+async fn restart_server(state: Arc<crate::AppState>) {
+    let stopping = match begin_stop(state.clone()) {
+        Ok(stopping) => stopping,
+        Err((_, msg)) => {
+            tracing::warn!("could not stop server to restart: {msg}");
+            return;
+        }
+    };
+
+    match stopping.await {
+        Ok(StopStep::ForceKill) => {
+            tracing::warn!("server had to be force killed, not restarting");
+            return;
+        }
+        Ok(_) => {}
+        Err(err) => {
+            tracing::warn!("stopping the server failed, not restarting: {err}");
+            return;
+        }
+    }
+
+    tracing::info!("server stopped, starting again");
+    let (status, msg) = start_server(state).await;
+    if !status.is_success() {
+        tracing::warn!("could not start server again: {msg}");
+    }
+}
@@ -1,10 +1,9 @@
-use std::sync::atomic::Ordering;
-
-use axum::extract::State;
+use axum::{Json, extract::State};
 
 use super::AppState;
+use crate::lifecycle::Status;
 
-/// returns the server running state
-pub async fn running(State(state): AppState) -> String {
-    state.server_running.load(Ordering::Relaxed).to_string()
+/// returns the server's lifecycle state
+pub async fn running(State(state): AppState) -> Json<Status> {
+    Json(state.lifecycle.status())
 }
@@ -1,39 +1,64 @@
-use std::sync::atomic::Ordering;
+use std::{
+    sync::{Arc, Mutex, atomic::Ordering},
+    time::SystemTime,
+};
 
 use axum::extract::State;
+use common::ServerState;
 use reqwest::StatusCode;
 use tokio::io::AsyncWriteExt;
 
 use super::AppState;
-use crate::games::{GameServer, Minecraft, Satisfactory, Terraria};
-use crate::{SERVER_PATH, SERVER_TYPE, ServerType, tasks, warn_error};
+use crate::config::config;
+use crate::persist::PersistedServer;
+use crate::{instances, tasks, warn_error};
+
+/// held while checking `max_running`, so two instances can't both start in the last free slot.
+static STARTING: Mutex<()> = Mutex::new(());
 
 pub async fn start(State(state): AppState) -> (StatusCode, &'static str) {
-    if state.server_running.load(Ordering::Relaxed) {
-        tracing::warn!("ignoring run request, already running");
-        return (StatusCode::TOO_MANY_REQUESTS, "already running..");
-    }
+    tracing::info!("got run request for {}", state.id);
 
-    if state.server_starting.load(Ordering::Relaxed) {
-        return (StatusCode::TOO_MANY_REQUESTS, "already starting up!");
-    }
+    start_server(state).await
+}
 
-    state.server_starting.store(true, Ordering::Release);
+/// spawns the instance's game server, unless it or too many others are already running.
+pub async fn start_server(state: Arc<crate::AppState>) -> (StatusCode, &'static str) {
+    let transition = {
+        let _starting = STARTING.lock().expect("not poisoned");
+        if let Some(max) = config().max_running
+            && !state.lifecycle.get().is_running()
+            && instances::running() >= max
+        {
+            tracing::warn!(
+                "not starting {}, {max} servers are already running",
+                state.id
+            );
+            return (StatusCode::TOO_MANY_REQUESTS, "too many servers running!");
+        }
 
-    let server_path = SERVER_PATH.as_path();
+        state.lifecycle.transition(ServerState::Starting)
+    };
+    if let Err(err) = transition {
+        return match err.from {
+            ServerState::Starting => (StatusCode::TOO_MANY_REQUESTS, "already starting up!"),
+            ServerState::Stopping => (StatusCode::TOO_MANY_REQUESTS, "still stopping!"),
+            _ => {
+                tracing::warn!("ignoring run request, already running");
+                (StatusCode::TOO_MANY_REQUESTS, "already running..")
+            }
+        };
+    }
 
-    tracing::info!("got run request");
+    let started = SystemTime::now();
+    state.console_tail.clear();
 
-    let run = match *SERVER_TYPE {
-        ServerType::Minecraft => Minecraft::run(state.clone(), server_path),
-        ServerType::Terraria => Terraria::run(state.clone(), server_path),
-        ServerType::Satisfactory => Satisfactory::run(state.clone(), server_path),
-    };
+    let run = state.game.run(state.clone());
 
     let child = match run {
         Ok(child) => child,
         Err(err) => {
-            state.server_starting.store(false, Ordering::Release);
+            state.set_stopped(ServerState::Stopped).await;
             return err;
         }
     };
@@ -41,51 +66,62 @@ pub async fn start(State(state): AppState) -> (StatusCode, &'static str) {
     let mut child = match child {
         Ok(child) => child,
         Err(err) => {
-            state.server_starting.store(false, Ordering::Release);
+            state.set_stopped(ServerState::Stopped).await;
             tracing::error!("could not start server: {err}");
             return (StatusCode::INTERNAL_SERVER_ERROR, "failed to run server");
         }
     };
 
-    state.server_starting.store(false, Ordering::Release);
-    state.server_running.store(true, Ordering::Release);
-
-    let Some(pid) = child.id() else {
-        warn_error!("could not get server pid");
-    };
-
-    let Some(mut stdin) = child.stdin.take() else {
-        warn_error!("could not get server stdin");
-    };
-    let Some(stdout) = child.stdout.take() else {
-        warn_error!("could not get server stdin");
-    };
-    let Some(stderr) = child.stderr.take() else {
-        warn_error!("could not get server stdin");
+    let (Some(pid), Some(mut stdin), Some(stdout), Some(stderr)) = (
+        child.id(),
+        child.stdin.take(),
+        child.stdout.take(),
+        child.stderr.take(),
+    ) else {
+        let _ = child.start_kill();
+        state.set_stopped(ServerState::Stopped).await;
+        warn_error!("could not get server pid or stdio");
     };
+    state.server_root_pid.store(pid, Ordering::Release);
 
-    // terraria-specific
-    if *SERVER_TYPE == ServerType::Terraria {
-        if cfg!(windows) {
-            tokio::spawn(tasks::child_finder(state.clone(), pid));
-        } else {
-            state.server_pid.store(pid, Ordering::Release);
-        }
-
-        if let Err(err) = stdin.write_u8(b'\n').await {
-            tracing::warn!("failed to write to stdin: {err}");
-        }
+    if state.game.wrapped() {
+        tokio::spawn(tasks::child_finder(state.clone(), pid, started));
     } else {
         state.server_pid.store(pid, Ordering::Release);
     }
 
+    if let Some(input) = state.game.startup_input()
+        && let Err(err) = stdin.write_all(input.as_bytes()).await
+    {
+        tracing::warn!("failed to write to stdin: {err}");
+    }
+
+    PersistedServer::current(&state, started).save(&state.id);
+
+    tokio::spawn(tasks::readiness_watcher(
+        state.clone(),
+        state.game.readiness(&state.config()),
+        state.console_channel.subscribe(),
+    ));
+    tokio::spawn(tasks::progress_watcher(
+        state.clone(),
+        state.console_channel.subscribe(),
+    ));
+
     tokio::spawn(tasks::console_writer(state.server_stdin.subscribe(), stdin));
-    tokio::spawn(tasks::console_reader(state.console_channel.clone(), stdout));
-    tokio::spawn(tasks::console_reader(state.console_channel.clone(), stderr));
+    let readers = vec![
+        tokio::spawn(tasks::console_reader(state.clone(), stdout)),
+        tokio::spawn(tasks::console_reader(state.clone(), stderr)),
+    ];
 
-    tokio::spawn(tasks::server_observer(state.clone(), child));
+    tokio::spawn(tasks::server_observer(
+        state.clone(),
+        child,
+        readers,
+        started,
+    ));
 
-    tracing::info!("server started!");
+    tracing::info!("server spawned, waiting for it to be ready");
 
-    (StatusCode::OK, "ran!")
+    (StatusCode::OK, "starting!")
 }
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{
+        State, WebSocketUpgrade,
+        ws::{Message, WebSocket},
+    },
+    response::Response,
+};
+use common::StateUpdate;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::AppState;
+
+/// streams the server's state and startup progress, starting with the current one.
+pub async fn state(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>) -> Response {
+    ws.on_upgrade(|socket| handle_socket(socket, state))
+}
+
+async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
+    let mut transitions = state.lifecycle.subscribe();
+    let mut progress = state.progress.subscribe();
+
+    loop {
+        let status = state.lifecycle.status();
+        let update = StateUpdate {
+            state: status.state,
+            since: status.since,
+            progress: progress.borrow_and_update().clone(),
+        };
+
+        match bitcode::serialize(&update) {
+            Ok(update) => {
+                if let Err(err) = socket.send(Message::binary(update)).await {
+                    tracing::warn!("{err}, closing ws");
+                    break;
+                }
+            }
+            Err(err) => tracing::error!("failed to serialize state: {err}"),
+        }
+
+        tokio::select! {
+            transition = transitions.recv() => match transition {
+                Ok(_) => {}
+                Err(RecvError::Lagged(lag)) => tracing::debug!("channel lagged {lag} msgs"),
+                Err(RecvError::Closed) => {
+                    tracing::warn!("channel closed");
+                    break;
+                }
+            },
+            changed = progress.changed() => {
+                if changed.is_err() {
+                    tracing::warn!("channel closed");
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -1,78 +1,78 @@
-use std::{sync::atomic::Ordering, time::Duration};
+use std::{sync::Arc, time::Duration};
 
-use axum::extract::State;
+use axum::extract::{Query, State};
+use common::ServerState;
 use reqwest::StatusCode;
-use runner::force_kill;
-use tracing::warn;
-
-use crate::games::{GameServer, Minecraft, Satisfactory, Terraria};
-use crate::{SERVER_TYPE, ServerType, routes::AppState};
-
-const WAIT_TIME: Duration = Duration::from_secs(10);
-const WAIT_INCRS: Duration = Duration::from_millis(500);
+use serde::Deserialize;
+use tokio::task::JoinHandle;
+
+use crate::{
+    countdown,
+    routes::AppState,
+    stopping::{self, StopStep},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct Delay {
+    /// seconds to count down in-game first.
+    #[serde(default)]
+    pub delay: u64,
+}
 
-pub async fn stop(State(state): AppState) -> (StatusCode, &'static str) {
-    if !state.server_running.load(Ordering::Relaxed) {
-        return (StatusCode::TOO_MANY_REQUESTS, "already stopped!");
+pub async fn stop(
+    State(state): AppState,
+    Query(Delay { delay }): Query<Delay>,
+) -> (StatusCode, &'static str) {
+    if delay == 0 || !state.lifecycle.get().is_running() {
+        if countdown::cancel(&state) {
+            tracing::info!("cancelled countdown, stopping now");
+        }
+        return stop_server(state).await;
     }
 
-    if state.server_stopping.load(Ordering::Relaxed) {
-        tracing::warn!("ignoring stop request, already stopping");
-        return (StatusCode::TOO_MANY_REQUESTS, "already stopping!");
+    let started = countdown::spawn(
+        &state,
+        "stopping",
+        Duration::from_secs(delay),
+        |state| async move {
+            stop_server(state).await;
+        },
+    );
+
+    if started {
+        (StatusCode::OK, "stopping soon!")
+    } else {
+        (StatusCode::TOO_MANY_REQUESTS, "already counting down!")
     }
+}
 
-    tracing::info!("received stop request");
-
-    state.server_stopping.store(true, Ordering::Release);
-
-    let stop = match *SERVER_TYPE {
-        ServerType::Minecraft => Minecraft::stop(state.clone()),
-        ServerType::Terraria => Terraria::stop(state.clone()),
-        ServerType::Satisfactory => Satisfactory::stop(state.clone()),
-    };
-
-    match stop {
-        Ok(()) => state.server_stopping.store(true, Ordering::Release),
-        Err(err) => warn!("failed to stop server: {err}"),
+/// gracefully stops the game server, escalating in the background if it does not stop in time.
+pub async fn stop_server(state: Arc<crate::AppState>) -> (StatusCode, &'static str) {
+    match begin_stop(state) {
+        Ok(_) => (StatusCode::OK, "stopped server!"),
+        Err(response) => response,
     }
+}
 
-    let state_1 = state.clone();
-    tokio::spawn(async move {
-        let loops = (WAIT_TIME.as_millis() / WAIT_INCRS.as_millis()) as usize;
-        for _ in 0..loops - 2 {
-            if !state_1.server_running.load(Ordering::Relaxed) {
-                state_1.server_stopping.store(false, Ordering::Release);
-                tracing::debug!("server stopped within {WAIT_TIME:?}!");
-                break;
+/// [`stop_server`], returning the task stopping the server, which resolves to the step that stopped it.
+pub fn begin_stop(
+    state: Arc<crate::AppState>,
+) -> Result<JoinHandle<StopStep>, (StatusCode, &'static str)> {
+    if let Err(err) = state.lifecycle.transition(ServerState::Stopping) {
+        return Err(match err.from {
+            ServerState::Stopping => {
+                tracing::warn!("ignoring stop request, already stopping");
+                (StatusCode::TOO_MANY_REQUESTS, "already stopping!")
             }
+            ServerState::Crashed if state.restarter.cancel() => {
+                tracing::info!("cancelled scheduled restart");
+                (StatusCode::OK, "cancelled restart!")
+            }
+            _ => (StatusCode::TOO_MANY_REQUESTS, "already stopped!"),
+        });
+    }
 
-            tokio::time::sleep(WAIT_INCRS).await;
-        }
-    });
-
-    // force killer
-    tokio::spawn(async move {
-        tokio::time::sleep(WAIT_TIME).await;
-
-        if !state.server_stopping.load(Ordering::Relaxed) {
-            return;
-        }
-
-        if !state.server_running.load(Ordering::Relaxed) {
-            return;
-        }
-
-        tracing::info!("server still running, killing now");
-
-        let pid = state.server_pid.load(Ordering::Relaxed);
-        if pid == 0 {
-            tracing::error!("server is running, but pid is 0?");
-        } else {
-            force_kill(pid);
-        }
-
-        state.server_stopping.store(false, Ordering::Release);
-    });
+    tracing::info!("received stop request");
 
-    (StatusCode::OK, "stopped server!")
+    Ok(tokio::spawn(async move { stopping::stop(&state).await }))
 }
@@ -0,0 +1,297 @@
+use std::{
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use common::ServerState;
+use regex::Regex;
+use runner::terminate;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    watch,
+};
+
+use crate::{AppState, config::StopConfig, lifecycle::Lifecycle};
+
+/// how long to wait for the game server to stop before escalating.
+#[derive(Debug, Clone)]
+pub struct StopPolicy {
+    /// how long to wait for the server to exit after asking it to stop.
+    pub timeout: Duration,
+    /// a console line showing the server has finished saving.
+    ///
+    /// if set and the server is still running after `timeout`, it gets up to `save_timeout` to print it,
+    /// then `timeout` again to exit.
+    pub saved: Option<Regex>,
+    pub save_timeout: Duration,
+}
+
+impl Default for StopPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            saved: None,
+            save_timeout: Duration::from_secs(2 * 60),
+        }
+    }
+}
+
+impl StopPolicy {
+    /// overrides `self` with what is set in the config.
+    pub fn with_config(mut self, config: &StopConfig) -> Self {
+        self.timeout = config.timeout.map_or(self.timeout, Duration::from_secs);
+        self.save_timeout = config
+            .save_timeout
+            .map_or(self.save_timeout, Duration::from_secs);
+
+        self
+    }
+}
+
+/// asks the server to stop, falling back to [`terminate`] if its console is not ours, eg. when it was adopted.
+///
+/// [`terminate`] is not possible on windows, which is why servers are never adopted there.
+fn ask(state: &Arc<AppState>) {
+    let Err(err) = state.game.stop(state.clone()) else {
+        return;
+    };
+    tracing::warn!("failed to stop server: {err}");
+
+    let pid = state.server_pid.load(Ordering::Acquire);
+    match terminate(pid) {
+        Ok(()) => tracing::info!("sent SIGTERM to {pid} instead"),
+        Err(err) => tracing::warn!("could not terminate {pid}: {err}"),
+    }
+}
+
+/// the step of [`stop`] that stopped the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopStep {
+    /// it exited within the timeout after being asked to.
+    Graceful,
+    /// it exited after it finished saving.
+    Saving,
+    /// it exited after being asked again.
+    Retry,
+    /// it was force killed.
+    ForceKill,
+}
+
+/// asks the game server to stop, escalating until it does, returning the step that stopped it.
+///
+/// the server should already be `Stopping`.
+pub async fn stop(state: &Arc<AppState>) -> StopStep {
+    let step = escalate(state).await;
+    match step {
+        StopStep::ForceKill => tracing::warn!("server stopped by {step:?}"),
+        _ => tracing::info!("server stopped by {step:?}"),
+    }
+    step
+}
+
+async fn escalate(state: &Arc<AppState>) -> StopStep {
+    let policy = state.stop_policy.read().expect("not poisoned").clone();
+    escalate_with(
+        &policy,
+        &state.lifecycle,
+        state.console_channel.subscribe(),
+        || ask(state),
+        || state.kill_server(),
+    )
+    .await
+}
+
+/// [`escalate`] with `ask` to ask the server to stop, and `kill` to force kill it.
+async fn escalate_with(
+    policy: &StopPolicy,
+    lifecycle: &Lifecycle,
+    mut console: broadcast::Receiver<String>,
+    ask: impl Fn(),
+    kill: impl FnOnce(),
+) -> StopStep {
+    // watch for the save line from before we ask, so it can't be missed.
+    let (saved_tx, mut saved) = watch::channel(false);
+    let watch_saved = async move {
+        if let Some(regex) = &policy.saved {
+            loop {
+                match console.recv().await {
+                    Ok(line) if regex.is_match(&line) => {
+                        saved_tx.send_replace(true);
+                        break;
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }
+        std::future::pending::<()>().await;
+    };
+
+    let stopped = || async {
+        lifecycle.wait_for(|s| s != ServerState::Stopping).await;
+    };
+    let stopped_within =
+        |timeout| async move { tokio::time::timeout(timeout, stopped()).await.is_ok() };
+
+    let steps = async move {
+        ask();
+        if stopped_within(policy.timeout).await {
+            return StopStep::Graceful;
+        }
+
+        if policy.saved.is_some() && !*saved.borrow() {
+            tracing::info!("server still running, waiting for it to finish saving");
+            let finished = tokio::select! {
+                () = stopped() => return StopStep::Saving,
+                saved = saved.wait_for(|s| *s) => saved.is_ok(),
+                () = tokio::time::sleep(policy.save_timeout) => false,
+            };
+
+            if finished {
+                tracing::info!("server finished saving, waiting for it to exit");
+                if stopped_within(policy.timeout).await {
+                    return StopStep::Saving;
+                }
+            } else {
+                tracing::warn!(
+                    "server did not finish saving within {:?}",
+                    policy.save_timeout
+                );
+            }
+        }
+
+        tracing::warn!("server still running, asking again");
+        ask();
+        if stopped_within(policy.timeout).await {
+            return StopStep::Retry;
+        }
+
+        tracing::warn!("server still running, force killing");
+        kill();
+        StopStep::ForceKill
+    };
+
+    tokio::select! {
+        step = steps => step,
+        () = watch_saved => unreachable!("never finishes"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, AtomicU32, Ordering},
+        },
+        time::Duration,
+    };
+
+    use common::ServerState;
+    use regex::Regex;
+    use tokio::sync::broadcast;
+
+    use super::{StopPolicy, StopStep, escalate_with};
+    use crate::lifecycle::Lifecycle;
+
+    /// a server that exits when it has been asked to stop `exits_after` times, returning the step and how many times
+    /// it was asked, and whether it was killed.
+    async fn stop(
+        policy: &StopPolicy,
+        exits_after: u32,
+        console: &broadcast::Sender<String>,
+        lifecycle: &Lifecycle,
+    ) -> (StopStep, u32, bool) {
+        let asked = AtomicU32::new(0);
+        let killed = AtomicBool::new(false);
+
+        let step = escalate_with(
+            policy,
+            lifecycle,
+            console.subscribe(),
+            || {
+                if asked.fetch_add(1, Ordering::Relaxed) + 1 == exits_after {
+                    lifecycle.transition(ServerState::Stopped).unwrap();
+                }
+            },
+            || killed.store(true, Ordering::Relaxed),
+        )
+        .await;
+
+        (step, asked.into_inner(), killed.into_inner())
+    }
+
+    fn stopping() -> Arc<Lifecycle> {
+        let lifecycle = Lifecycle::new();
+        for state in [
+            ServerState::Starting,
+            ServerState::Ready,
+            ServerState::Stopping,
+        ] {
+            lifecycle.transition(state).unwrap();
+        }
+        Arc::new(lifecycle)
+    }
+
+    #[tokio::test]
+    async fn escalate() {
+        let policy = StopPolicy {
+            timeout: Duration::from_millis(50),
+            ..StopPolicy::default()
+        };
+        let console = broadcast::channel(16).0;
+
+        for (exits_after, expected) in [
+            (1, (StopStep::Graceful, 1, false)),
+            (2, (StopStep::Retry, 2, false)),
+            (0, (StopStep::ForceKill, 2, true)),
+        ] {
+            let lifecycle = stopping();
+            assert_eq!(
+                stop(&policy, exits_after, &console, &lifecycle).await,
+                expected
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn escalate_saving() {
+        let policy = StopPolicy {
+            timeout: Duration::from_millis(200),
+            saved: Some(Regex::new("Saved").unwrap()),
+            save_timeout: Duration::from_secs(5),
+        };
+        let console = broadcast::channel(16).0;
+
+        // finishes saving after the first timeout, then exits.
+        let lifecycle = stopping();
+        let server = {
+            let console = console.clone();
+            let lifecycle = lifecycle.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                console.send("Saving...".to_string()).unwrap();
+                console.send("Saved the world".to_string()).unwrap();
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                lifecycle.transition(ServerState::Stopped).unwrap();
+            })
+        };
+        assert_eq!(
+            stop(&policy, 0, &console, &lifecycle).await,
+            (StopStep::Saving, 1, false)
+        );
+        server.await.unwrap();
+
+        // never finishes saving.
+        let policy = StopPolicy {
+            timeout: Duration::from_millis(50),
+            save_timeout: Duration::from_millis(50),
+            ..policy
+        };
+        let lifecycle = stopping();
+        assert_eq!(
+            stop(&policy, 0, &console, &lifecycle).await,
+            (StopStep::ForceKill, 2, true)
+        );
+    }
+}
@@ -1,32 +1,45 @@
 use std::{
-    env,
-    sync::{Arc, LazyLock, atomic::Ordering},
-    time::Duration,
+    net::Ipv4Addr,
+    process::ExitStatus,
+    sync::{Arc, atomic::Ordering},
+    time::{Duration, SystemTime},
 };
 
 use children::get_children;
-use common::Stats;
-use runner::force_kill;
+use common::{Progress, ServerState, Stats};
+use futures_util::future;
 use sysinfo::{Cpu, MemoryRefreshKind, Pid, ProcessRefreshKind, RefreshKind, System};
 use tokio::{
     io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+    net::TcpStream,
     process::{Child, ChildStdin},
     signal,
-    sync::broadcast,
+    sync::broadcast::{self, error::RecvError},
+    task::{self, JoinHandle, JoinSet},
 };
 use tracing::instrument;
 
 use crate::{
-    AppState, SERVER_TYPE, ServerType,
-    games::{GameServer, Minecraft, Satisfactory, Terraria},
+    AppState,
+    config::config,
+    crash,
+    games::Readiness,
+    instances,
+    persist::{self, PersistedServer},
+    restart::{ExitKind, ServerExit},
+    stopping,
 };
 
-/// how many times to wait for the server to shutdown
-const SERVER_SHUTDOWN_RETRIES: u32 = 3;
+/// how long to wait for the server to become ready before assuming it is.
+const READY_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// how long to wait for the rest of the server's output after it exits.
+///
+/// its pipes may be held open by processes it left behind.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// ensures graceful shutdown
 #[instrument(skip_all)]
-pub async fn shutdown(state: Arc<AppState>) {
+pub async fn shutdown() {
     let ctrl_c = async {
         signal::ctrl_c()
             .await
@@ -51,96 +64,96 @@ pub async fn shutdown(state: Arc<AppState>) {
 
     tracing::info!("shutting down..");
 
-    if !state.server_running.load(Ordering::Relaxed) {
-        return;
+    let mut stopping = JoinSet::new();
+    for state in instances::all() {
+        stopping.spawn(shutdown_instance(state.clone()));
     }
+    stopping.join_all().await;
+}
 
-    let stop = match *SERVER_TYPE {
-        ServerType::Minecraft => Minecraft::stop,
-        ServerType::Terraria => Terraria::stop,
-        ServerType::Satisfactory => Satisfactory::stop,
-    };
-
-    match stop(state.clone()) {
-        Ok(()) => state.server_running.store(false, Ordering::Release),
-        Err(err) => tracing::warn!("could not stop server: {err}"),
+/// stops the instance's server if it is running, unless it is left for the next runner.
+#[instrument(skip_all, fields(instance = %state.id))]
+async fn shutdown_instance(state: Arc<AppState>) {
+    if !state.lifecycle.get().is_running() {
+        return;
     }
 
-    let mut retries = 0;
-    while state.server_running.load(Ordering::Relaxed) {
-        if retries == SERVER_SHUTDOWN_RETRIES {
-            tracing::warn!("reached maximum retries, shutting down anyway");
-            break;
-        }
-
-        tracing::debug!("waiting for server to stop");
-        tokio::time::sleep(Duration::from_secs(1)).await;
-        retries += 1;
-
-        match stop(state.clone()) {
-            Ok(()) => state.server_running.store(false, Ordering::Release),
-            Err(err) => tracing::warn!("could not stop server: {err}"),
-        }
+    if config().detach_on_shutdown && state.lifecycle.get() == ServerState::Ready {
+        tracing::info!("leaving the server running for the next runner to adopt");
+        return;
     }
 
-    if !state.server_running.load(Ordering::Relaxed) {
-        tracing::info!("shut down game server gracefully");
-        return;
+    // it may already be stopping.
+    if let Err(err) = state.lifecycle.transition(ServerState::Stopping) {
+        tracing::debug!("{err}");
     }
 
-    let pid = state.server_pid.load(Ordering::Relaxed);
-    if pid != 0 {
-        tracing::warn!("force killing server with pid {pid}");
-        force_kill(pid);
-    } else {
-        tracing::warn!("could not force kill because pid is 0");
+    stopping::stop(&state).await;
+}
+
+/// reloads the config whenever we get `SIGHUP`.
+#[cfg(unix)]
+#[instrument(skip_all)]
+pub async fn reload_on_sighup() {
+    let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())
+        .expect("failed to install signal handler");
+
+    while hangup.recv().await.is_some() {
+        tracing::info!("got SIGHUP, reloading config");
+        if let Err(problems) = instances::reload() {
+            tracing::warn!("not reloading config:");
+            for problem in problems {
+                tracing::warn!("- {problem}");
+            }
+        }
     }
 }
 
-/// a background task that refreshes and broadcasts system stats.
+/// a background task that refreshes and broadcasts the system stats, with each instance's server stats.
 #[instrument(skip_all)]
-pub fn stats_refresher(app_state: &Arc<AppState>) {
+pub fn stats_refresher() {
     let mut system = System::new_with_specifics(RefreshKind::everything().without_processes());
     // Wait a bit because CPU usage is based on diff.
     std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
     // Refresh CPUs again to get actual value.
     system.refresh_cpu_usage();
 
-    let tx = &app_state.stats_channel;
-
     loop {
-        let mut stats = Stats {
-            system_cpu_usage: system.cpus().iter().map(Cpu::cpu_usage).collect(),
-            system_ram_free: system.available_memory(),
-            system_ram_used: system.used_memory(),
-            server_ram_usage: None,
-            server_cpu_usage: None,
-            server_disk_usage: None,
-        };
-
-        // system.processes_by_exact_name(name)
-
-        let pid = app_state.server_pid.load(Ordering::Relaxed);
-        if pid != 0 {
-            let pid = Pid::from_u32(pid);
-
+        let pids: Vec<_> = instances::all()
+            .map(|state| state.server_pid.load(Ordering::Relaxed))
+            .filter(|pid| *pid != 0)
+            .map(Pid::from_u32)
+            .collect();
+        if !pids.is_empty() {
             system.refresh_processes_specifics(
-                sysinfo::ProcessesToUpdate::Some(&[pid]),
+                sysinfo::ProcessesToUpdate::Some(&pids),
                 true,
                 ProcessRefreshKind::everything(),
             );
+        }
 
-            if let Some(process) = system.process(pid) {
+        for state in instances::all() {
+            let mut stats = Stats {
+                system_cpu_usage: system.cpus().iter().map(Cpu::cpu_usage).collect(),
+                system_ram_free: system.available_memory(),
+                system_ram_used: system.used_memory(),
+                server_ram_usage: None,
+                server_cpu_usage: None,
+                server_disk_usage: None,
+            };
+
+            let pid = state.server_pid.load(Ordering::Relaxed);
+            if pid != 0
+                && let Some(process) = system.process(Pid::from_u32(pid))
+            {
                 stats.server_ram_usage = Some(process.memory());
                 stats.server_cpu_usage = Some(process.cpu_usage());
                 let disk = process.disk_usage();
                 stats.server_disk_usage = Some(disk.read_bytes + disk.written_bytes);
             }
-        }
 
-        if tx.send(stats).is_err() {
-            tracing::warn!("channel closed, quitting");
-            return;
+            // fails if no one is watching, which is fine.
+            let _ = state.stats_channel.send(stats);
         }
 
         std::thread::sleep(Duration::from_secs(1));
@@ -153,16 +166,217 @@ pub fn stats_refresher(app_state: &Arc<AppState>) {
     }
 }
 
-/// waits for the server ([`Child`]) to stop
-#[instrument(skip_all)]
-pub async fn server_observer(state: Arc<AppState>, mut child: Child) {
-    if let Err(err) = child.wait().await {
-        tracing::warn!("could not wait for server exit: {err}");
+/// waits for the server ([`Child`]) to stop, then handles its exit with [`on_exit`].
+///
+/// `readers` are its [`console_reader`]s, which are given time to finish so the crash report has all of the output.
+#[instrument(skip_all, fields(instance = %state.id))]
+pub async fn server_observer(
+    state: Arc<AppState>,
+    mut child: Child,
+    readers: Vec<JoinHandle<()>>,
+    started: SystemTime,
+) {
+    let status = child.wait().await;
+    if tokio::time::timeout(DRAIN_TIMEOUT, future::join_all(readers))
+        .await
+        .is_err()
+    {
+        tracing::warn!("server output still open {DRAIN_TIMEOUT:?} after it exited");
     }
+    on_exit(state, status, started).await;
+}
 
-    state.set_stopped().await;
+/// polls a server started by a previous runner until it stops, then handles its exit with [`on_exit`].
+#[instrument(skip_all, fields(instance = %state.id))]
+pub async fn adopted_observer(state: Arc<AppState>, pid: u32, started: SystemTime) {
+    let mut system = System::new();
+    let start_time = persist::process_start_time(&mut system, pid);
+    while start_time.is_some() && persist::process_start_time(&mut system, pid) == start_time {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
 
-    tracing::info!("server stopped");
+    let status = Err(std::io::Error::other(
+        "exit status unknown, the server was adopted",
+    ));
+    on_exit(state, status, started).await;
+}
+
+/// records why the server stopped, and restarts it if it crashed.
+///
+/// if it crashed, the game's crash artifacts written since `started` are saved with the console in a crash report.
+/// an adopted server that stopped by itself is not restarted, since it may have been stopped on purpose.
+async fn on_exit(state: Arc<AppState>, status: std::io::Result<ExitStatus>, started: SystemTime) {
+    let requested = state.lifecycle.get() == ServerState::Stopping;
+
+    let kind = match &status {
+        _ if requested => ExitKind::Requested,
+        _ if state.adopted.load(Ordering::Acquire) => {
+            tracing::warn!("adopted server exited, its exit status is unknown");
+            ExitKind::Unknown
+        }
+        Ok(status) if status.success() => ExitKind::Clean,
+        Ok(status) => {
+            tracing::warn!("server exited unexpectedly: {status}");
+            ExitKind::Crashed
+        }
+        Err(err) => {
+            tracing::warn!("server exited with an unknown status: {err}");
+            ExitKind::Crashed
+        }
+    };
+
+    let status_msg = match &status {
+        Ok(status) => status.to_string(),
+        Err(err) => err.to_string(),
+    };
+
+    if kind == ExitKind::Crashed {
+        let console = state.console_tail.lines();
+        let status_msg = status_msg.clone();
+        let game = state.game;
+        let instance = state.config();
+        let captured = task::spawn_blocking(move || {
+            let artifacts = game.crash_artifacts(&instance.server_dir, started);
+            crash::capture(
+                &instance.crash_dir,
+                &artifacts,
+                &console,
+                &status_msg,
+                config().crash.keep,
+            )
+        })
+        .await;
+
+        match captured {
+            Ok(Ok(id)) => tracing::info!("saved crash report {id}"),
+            Ok(Err(err)) => tracing::warn!("could not save crash report: {err:#}"),
+            Err(err) => tracing::warn!("crash report task failed: {err}"),
+        }
+    }
+
+    let next = if kind == ExitKind::Crashed {
+        ServerState::Crashed
+    } else {
+        ServerState::Stopped
+    };
+    state.set_stopped(next).await;
+
+    tracing::info!("server stopped ({kind:?})");
+
+    // schedule after `set_stopped`, so the restart can't start before we are `Crashed`.
+    let restart_in = if kind == ExitKind::Crashed {
+        state.restarter.schedule(state.clone())
+    } else {
+        None
+    };
+
+    let exit = ServerExit {
+        kind,
+        at: SystemTime::now(),
+        code: status.as_ref().ok().and_then(ExitStatus::code),
+        status: status_msg,
+        restart_at: restart_in.map(|delay| SystemTime::now() + delay),
+    };
+    state.last_exit.write().await.replace(exit);
+}
+
+/// marks the server as ready once its [`Readiness`] probe succeeds.
+///
+/// `console` should be subscribed before the server's output is read, so no lines are missed.
+#[instrument(skip_all, fields(instance = %state.id))]
+pub async fn readiness_watcher(
+    state: Arc<AppState>,
+    readiness: Readiness,
+    mut console: broadcast::Receiver<String>,
+) {
+    let probe = async {
+        match readiness {
+            Readiness::Console(regex) => loop {
+                match console.recv().await {
+                    Ok(line) if regex.is_match(&line) => break,
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(lag)) => {
+                        tracing::warn!("readiness probe lagged {lag} lines");
+                    }
+                    // the server exited, `not_starting` will resolve.
+                    Err(RecvError::Closed) => std::future::pending().await,
+                }
+            },
+            Readiness::Port(port) => {
+                while TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+                    .await
+                    .is_err()
+                {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    };
+    let not_starting = state.lifecycle.wait_for(|s| s != ServerState::Starting);
+
+    tokio::select! {
+        ready = tokio::time::timeout(READY_TIMEOUT, probe) => {
+            if ready.is_err() {
+                tracing::warn!("server not ready after {READY_TIMEOUT:?}, assuming it is");
+            }
+        }
+        _ = not_starting => {
+            tracing::debug!("server no longer starting, stopped waiting");
+            return;
+        }
+    }
+
+    match state.lifecycle.transition(ServerState::Ready) {
+        Ok(_) => {
+            let took = state.lifecycle.startup_duration().unwrap_or_default();
+            tracing::info!("server ready after {took:?}");
+        }
+        Err(err) => tracing::debug!("{err}"),
+    }
+}
+
+/// tracks the server's startup progress from its console while it is starting.
+#[instrument(skip_all, fields(instance = %state.id))]
+pub async fn progress_watcher(state: Arc<AppState>, mut console: broadcast::Receiver<String>) {
+    state
+        .progress
+        .send_replace(Some(Progress::new(0, "starting")));
+
+    let watch = async {
+        loop {
+            match console.recv().await {
+                Ok(line) => {
+                    let Some(progress) = state.game.progress(&line) else {
+                        continue;
+                    };
+                    state.progress.send_if_modified(|current| {
+                        // never go backwards, some lines repeat while starting.
+                        if current
+                            .as_ref()
+                            .is_some_and(|c| c.percent > progress.percent || *c == progress)
+                        {
+                            return false;
+                        }
+                        *current = Some(progress);
+                        true
+                    });
+                }
+                Err(RecvError::Lagged(lag)) => {
+                    tracing::debug!("progress lagged {lag} lines");
+                }
+                // the server exited, `not_starting` will resolve.
+                Err(RecvError::Closed) => std::future::pending().await,
+            }
+        }
+    };
+    let not_starting = state.lifecycle.wait_for(|s| s != ServerState::Starting);
+
+    tokio::select! {
+        () = watch => {}
+        _ = not_starting => {}
+    }
+
+    state.progress.send_replace(None);
 }
 
 #[instrument(skip_all)]
@@ -177,15 +391,14 @@ pub async fn console_writer(mut rx: broadcast::Receiver<String>, mut stdin: Chil
     }
 }
 
-static SHOW_CONSOLE: LazyLock<bool> =
-    LazyLock::new(|| env::var("SHOW_CONSOLE").is_ok_and(|v| v == "true"));
-
 /// a background task that reads the stdout of the server (if running)
-#[instrument(skip_all)]
-pub async fn console_reader<C: AsyncRead + Unpin>(tx: broadcast::Sender<String>, console: C) {
+///
+/// lines are kept in the console tail for crash reports, then broadcast.
+#[instrument(skip_all, fields(instance = %state.id))]
+pub async fn console_reader<C: AsyncRead + Unpin>(state: Arc<AppState>, console: C) {
     let mut console = BufReader::new(console);
 
-    let mut log = if *SHOW_CONSOLE {
+    let mut log = if config().show_console {
         Some(tokio::io::stdout())
     } else {
         None
@@ -211,7 +424,8 @@ pub async fn console_reader<C: AsyncRead + Unpin>(tx: broadcast::Sender<String>,
             let _ = log.write_u8(b'\n').await;
         }
 
-        if let Err(err) = tx.send(line) {
+        state.console_tail.push(line.clone());
+        if let Err(err) = state.console_channel.send(line) {
             tracing::warn!("failed to broadcast: {err}");
         }
     };
@@ -220,12 +434,12 @@ pub async fn console_reader<C: AsyncRead + Unpin>(tx: broadcast::Sender<String>,
 }
 
 /// gets the real pid after it spawns
-#[instrument(skip_all)]
-pub async fn child_finder(state: Arc<AppState>, parent: u32) {
+#[instrument(skip_all, fields(instance = %state.id))]
+pub async fn child_finder(state: Arc<AppState>, parent: u32, started: SystemTime) {
     loop {
         tokio::time::sleep(Duration::from_secs(1)).await;
 
-        if !state.server_running.load(Ordering::Acquire) {
+        if !state.lifecycle.get().is_running() {
             tracing::warn!("server not running, stopping");
             break;
         }
@@ -244,6 +458,7 @@ pub async fn child_finder(state: Arc<AppState>, parent: u32) {
         tracing::info!("found real child! ({})", child.pid);
 
         state.server_pid.store(child.pid, Ordering::Release);
+        PersistedServer::current(&state, started).save(&state.id);
         return;
     }
 }